@@ -0,0 +1,94 @@
+// Целое AML: ZeroOp, OneOp, OnesOp или Byte/Word/DWordPrefix
+fn aml_integer(aml: &[u8], pos: &mut usize) -> Option<u64> {
+    let op = *aml.get(*pos)?;
+    *pos += 1;
+    let size = match op {
+        0x00 => return Some(0),
+        0x01 => return Some(1),
+        0xFF => return Some(u64::MAX),
+        0x0A => 1,
+        0x0B => 2,
+        0x0C => 4,
+        _ => return None,
+    };
+    let bytes = aml.get(*pos..*pos + size)?;
+    *pos += size;
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | byte as u64),
+    )
+}
+
+// Ищет в DSDT `Name(\_S5, Package() { SLP_TYPa, SLP_TYPb, ... })`.
+// Полный интерпретатор AML не нужен: объект почти всегда объявлен статически.
+// Имя `_S5_` может встретиться и раньше (в ссылке, методе), поэтому
+// проверяется каждое вхождение.
+pub fn parse_s5(aml: &[u8]) -> Option<(u16, u16)> {
+    aml.windows(4)
+        .enumerate()
+        .filter(|(_, window)| *window == b"_S5_")
+        .find_map(|(start, _)| parse_s5_at(aml, start))
+}
+
+// `start` — позиция имени `_S5_`
+fn parse_s5_at(aml: &[u8], start: usize) -> Option<(u16, u16)> {
+    // Перед именем должен стоять NameOp (возможно, с корневым префиксом '\')
+    let name_op = match start {
+        1.. if aml[start - 1] == 0x08 => true,
+        2.. if aml[start - 1] == b'\\' && aml[start - 2] == 0x08 => true,
+        _ => false,
+    };
+    if !name_op {
+        return None;
+    }
+
+    let mut pos = start + 4;
+    if *aml.get(pos)? != 0x12 {
+        return None; // Не PackageOp
+    }
+    pos += 1;
+    // PkgLength: старшие два бита первого байта — число дополнительных байтов
+    pos += 1 + (*aml.get(pos)? >> 6) as usize;
+    pos += 1; // NumElements
+
+    let slp_typa = aml_integer(aml, &mut pos)?;
+    let slp_typb = aml_integer(aml, &mut pos)?;
+    Some((slp_typa as u16, slp_typb as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_s5;
+
+    #[test]
+    fn qemu_s5() {
+        // Name (_S5, Package (0x04) { Zero, Zero, Zero, Zero }) из DSDT QEMU
+        let aml = [
+            0x10, 0x08, b'_', b'S', b'5', b'_', 0x12, 0x06, 0x04, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(parse_s5(&aml), Some((0, 0)));
+    }
+
+    #[test]
+    fn prefixed_values() {
+        // Name (\_S5, Package (0x02) { 0x07, 0x0105 })
+        let aml = [
+            0x08, b'\\', b'_', b'S', b'5', b'_', 0x12, 0x08, 0x02, 0x0A, 0x07, 0x0B, 0x05, 0x01,
+        ];
+        assert_eq!(parse_s5(&aml), Some((7, 0x105)));
+    }
+
+    #[test]
+    fn skips_other_occurrences() {
+        // Сначала _S5_ не после NameOp, затем Name (_S5, Package (0x02) { 0x05, 0x05 })
+        let aml = [
+            0x14, b'_', b'S', b'5', b'_', 0x12, 0x06, 0x02, 0x00, 0x00, //
+            0x08, b'_', b'S', b'5', b'_', 0x12, 0x06, 0x02, 0x0A, 0x05, 0x0A, 0x05,
+        ];
+        assert_eq!(parse_s5(&aml), Some((5, 5)));
+        assert_eq!(parse_s5(&aml[..10]), None);
+        assert_eq!(parse_s5(b"no sleep states"), None);
+    }
+}
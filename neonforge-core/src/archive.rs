@@ -0,0 +1,110 @@
+// Простой архив файлов, в котором /etc сохраняется на диск.
+// Заголовок: сигнатура, длина данных и их контрольная сумма (FNV-1a).
+// Затем записи: u16 длина имени, имя, u32 длина содержимого, содержимое.
+// Все числа — little-endian.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const MAGIC: [u8; 8] = *b"NEONETC1";
+pub const HEADER_SIZE: usize = 16;
+
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811C_9DC5, |hash: u32, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+pub fn pack(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, data) in files {
+        body.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        body.extend_from_slice(name.as_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+    }
+
+    let mut archive = Vec::with_capacity(HEADER_SIZE + body.len());
+    archive.extend_from_slice(&MAGIC);
+    archive.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&checksum(&body).to_le_bytes());
+    archive.extend_from_slice(&body);
+    archive
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+// Полный размер архива по его заголовку; None, если сигнатуры нет
+pub fn packed_len(header: &[u8]) -> Option<usize> {
+    if header.get(..MAGIC.len())? != MAGIC {
+        return None;
+    }
+    Some(HEADER_SIZE + read_u32(header, 8)? as usize)
+}
+
+// None, если архив повреждён или обрезан
+pub fn unpack(archive: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    let body = archive.get(HEADER_SIZE..packed_len(archive)?)?;
+    if checksum(body) != read_u32(archive, 12)? {
+        return None;
+    }
+
+    let mut files = Vec::new();
+    let mut pos = 0;
+    while pos < body.len() {
+        let name_len = u16::from_le_bytes(body.get(pos..pos + 2)?.try_into().ok()?) as usize;
+        pos += 2;
+        let name = core::str::from_utf8(body.get(pos..pos + name_len)?).ok()?;
+        pos += name_len;
+        let len = read_u32(body, pos)? as usize;
+        pos += 4;
+        files.push((String::from(name), body.get(pos..pos + len)?.to_vec()));
+        pos += len;
+    }
+    Some(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack, packed_len, unpack, HEADER_SIZE};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    fn sample() -> Vec<(String, Vec<u8>)> {
+        vec![
+            (String::from("hostname"), b"neonforge\n".to_vec()),
+            (String::from("neonrc"), b"alias ll='ls -a'\n".to_vec()),
+            (String::from("empty"), Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let archive = pack(&sample());
+        assert_eq!(packed_len(&archive[..HEADER_SIZE]), Some(archive.len()));
+        assert_eq!(unpack(&archive), Some(sample()));
+        assert_eq!(unpack(&pack(&[])), Some(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_damage() {
+        // Чистый диск: сигнатуры нет
+        assert_eq!(packed_len(&[0; 512]), None);
+        assert_eq!(unpack(&[0; 512]), None);
+
+        let mut archive = pack(&sample());
+        assert_eq!(unpack(&archive[..archive.len() - 1]), None);
+        let last = archive.len() - 1;
+        archive[last] ^= 1;
+        assert_eq!(unpack(&archive), None);
+    }
+
+    #[test]
+    fn trailing_bytes_ignored() {
+        // Архив дополняется до целого числа секторов
+        let mut archive = pack(&sample());
+        archive.resize(1024, 0);
+        assert_eq!(unpack(&archive), Some(sample()));
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Описание опции команды: `--name`, необязательное `-s` и признак значения
+#[derive(Clone, Copy)]
+pub struct OptSpec {
+    pub name: &'static str,
+    pub short: Option<char>,
+    pub takes_value: bool,
+}
+
+// Какие опции принимает команда и сколько позиционных аргументов
+#[derive(Clone, Copy)]
+pub struct ArgSpec {
+    pub options: &'static [OptSpec],
+    pub min: usize,
+    pub max: usize,
+}
+
+impl ArgSpec {
+    pub const NONE: ArgSpec = ArgSpec {
+        options: &[],
+        min: 0,
+        max: 0,
+    };
+}
+
+#[derive(Default)]
+pub struct Args<'a> {
+    pub positional: Vec<&'a str>,
+    options: Vec<(&'static str, Option<&'a str>)>,
+}
+
+impl<'a> Args<'a> {
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| *option == name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&'a str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| *option == name)
+            .and_then(|(_, value)| *value)
+    }
+}
+
+// Разбирает `--flag`, `--flag value`, `--flag=value` и `-f value` и проверяет
+// число позиционных аргументов. `argv[0]` — имя команды, `--` завершает опции.
+// У команд без опций аргументы вида `-x` остаются позиционными (нужно для `test`).
+pub fn parse<'a>(argv: &[&'a str], spec: &ArgSpec) -> Result<Args<'a>, String> {
+    let mut parsed = Args {
+        positional: Vec::new(),
+        options: Vec::new(),
+    };
+    let mut rest = argv.iter().skip(1);
+
+    while let Some(&arg) = rest.next() {
+        if spec.options.is_empty() {
+            parsed.positional.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            parsed.positional.extend(rest);
+            break;
+        }
+
+        let (option, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            let option = spec.options.iter().find(|o| o.name == name);
+            (
+                option.ok_or_else(|| format!("unknown option --{}", name))?,
+                value,
+            )
+        } else if arg.len() == 2 && arg.starts_with('-') && arg != "-" {
+            let short = arg[1..].chars().next();
+            let option = spec
+                .options
+                .iter()
+                .find(|o| o.short.is_some() && o.short == short);
+            (
+                option.ok_or_else(|| format!("unknown option {}", arg))?,
+                None,
+            )
+        } else {
+            parsed.positional.push(arg);
+            continue;
+        };
+
+        let value = if option.takes_value {
+            match inline_value {
+                Some(value) => Some(value),
+                None => Some(
+                    *rest
+                        .next()
+                        .ok_or_else(|| format!("option --{} needs a value", option.name))?,
+                ),
+            }
+        } else if inline_value.is_some() {
+            return Err(format!("option --{} takes no value", option.name));
+        } else {
+            None
+        };
+        parsed.options.push((option.name, value));
+    }
+
+    if parsed.positional.len() < spec.min {
+        return Err(String::from("missing argument"));
+    }
+    if parsed.positional.len() > spec.max {
+        return Err(String::from("too many arguments"));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, ArgSpec, OptSpec};
+
+    const SPEC: ArgSpec = ArgSpec {
+        options: &[
+            OptSpec {
+                name: "all",
+                short: Some('a'),
+                takes_value: false,
+            },
+            OptSpec {
+                name: "count",
+                short: Some('n'),
+                takes_value: true,
+            },
+        ],
+        min: 0,
+        max: 2,
+    };
+
+    #[test]
+    fn flags_and_values() {
+        let args = parse(&["cmd", "-a", "--count", "5", "file"], &SPEC).unwrap();
+        assert!(args.flag("all"));
+        assert_eq!(args.value("count"), Some("5"));
+        assert_eq!(args.positional, ["file"]);
+
+        let args = parse(&["cmd", "--count=7", "-n", "8"], &SPEC).unwrap();
+        assert_eq!(args.value("count"), Some("8"));
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let args = parse(&["cmd", "--", "-a", "--count"], &SPEC).unwrap();
+        assert!(!args.flag("all"));
+        assert_eq!(args.positional, ["-a", "--count"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["cmd", "--bogus"], &SPEC).is_err());
+        assert!(parse(&["cmd", "-x"], &SPEC).is_err());
+        assert!(parse(&["cmd", "--count"], &SPEC).is_err());
+        assert!(parse(&["cmd", "--all=yes"], &SPEC).is_err());
+        assert!(parse(&["cmd", "a", "b", "c"], &SPEC).is_err());
+    }
+
+    #[test]
+    fn no_options_keeps_dashes_positional() {
+        let spec = ArgSpec {
+            options: &[],
+            min: 0,
+            max: 3,
+        };
+        let args = parse(&["test", "-n", "x"], &spec).unwrap();
+        assert_eq!(args.positional, ["-n", "x"]);
+    }
+}
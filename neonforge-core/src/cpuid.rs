@@ -0,0 +1,311 @@
+// Разбор результатов инструкции CPUID. Сами листы читает ядро, здесь —
+// только их декодирование.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Regs {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    Ebx,
+    Ecx,
+    Edx,
+}
+
+impl Regs {
+    pub fn get(&self, reg: Reg) -> u32 {
+        match reg {
+            Reg::Ebx => self.ebx,
+            Reg::Ecx => self.ecx,
+            Reg::Edx => self.edx,
+        }
+    }
+}
+
+// Строка производителя из листа 0: EBX, EDX, ECX
+pub fn vendor(leaf0: Regs) -> [u8; 12] {
+    let mut vendor = [0; 12];
+    vendor[..4].copy_from_slice(&leaf0.ebx.to_le_bytes());
+    vendor[4..8].copy_from_slice(&leaf0.edx.to_le_bytes());
+    vendor[8..].copy_from_slice(&leaf0.ecx.to_le_bytes());
+    vendor
+}
+
+// Название процессора из листов 0x80000002..0x80000004
+pub fn brand(leaves: [Regs; 3]) -> [u8; 48] {
+    let mut brand = [0; 48];
+    for (i, regs) in leaves.iter().enumerate() {
+        for (j, reg) in [regs.eax, regs.ebx, regs.ecx, regs.edx].iter().enumerate() {
+            let at = i * 16 + j * 4;
+            brand[at..at + 4].copy_from_slice(&reg.to_le_bytes());
+        }
+    }
+    brand
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+}
+
+// Семейство и модель с учётом расширенных полей EAX листа 1
+pub fn signature(eax: u32) -> Signature {
+    let base_family = (eax >> 8) & 0xF;
+    let base_model = (eax >> 4) & 0xF;
+    let family = if base_family == 0xF {
+        base_family + ((eax >> 20) & 0xFF)
+    } else {
+        base_family
+    };
+    let model = if base_family == 0x6 || base_family == 0xF {
+        ((eax >> 16) & 0xF) << 4 | base_model
+    } else {
+        base_model
+    };
+    Signature {
+        family,
+        model,
+        stepping: eax & 0xF,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    Data,
+    Instruction,
+    Unified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cache {
+    pub level: u32,
+    pub kind: CacheKind,
+    pub size: u32, // Байт
+    pub ways: u32,
+    pub line: u32,
+    pub sets: u32,
+    pub shared_by: u32, // Логических процессоров на один экземпляр кэша
+}
+
+// Описание кэша из листа 4 (Intel) или 0x8000001D (AMD). None — конец списка.
+pub fn decode_cache(regs: Regs) -> Option<Cache> {
+    let kind = match regs.eax & 0x1F {
+        1 => CacheKind::Data,
+        2 => CacheKind::Instruction,
+        3 => CacheKind::Unified,
+        _ => return None,
+    };
+    let ways = (regs.ebx >> 22) + 1;
+    let partitions = ((regs.ebx >> 12) & 0x3FF) + 1;
+    let line = (regs.ebx & 0xFFF) + 1;
+    let sets = regs.ecx + 1;
+    Some(Cache {
+        level: (regs.eax >> 5) & 0x7,
+        kind,
+        size: ways * partitions * line * sets,
+        ways,
+        line,
+        sets,
+        shared_by: ((regs.eax >> 14) & 0xFFF) + 1,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Fpu,
+    Tsc,
+    Msr,
+    Pae,
+    Apic,
+    Pge,
+    Mmx,
+    Sse,
+    Sse2,
+    Htt,
+    Sse3,
+    Ssse3,
+    Sse41,
+    Sse42,
+    Pcid,
+    X2Apic,
+    TscDeadline,
+    Xsave,
+    Avx,
+    Rdrand,
+    Hypervisor,
+    Fsgsbase,
+    Avx2,
+    Smep,
+    Rdseed,
+    Smap,
+    Syscall,
+    Nx,
+    Pages1G,
+    Rdtscp,
+    LongMode,
+    InvariantTsc,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 32] = [
+        Feature::Fpu,
+        Feature::Tsc,
+        Feature::Msr,
+        Feature::Pae,
+        Feature::Apic,
+        Feature::Pge,
+        Feature::Mmx,
+        Feature::Sse,
+        Feature::Sse2,
+        Feature::Htt,
+        Feature::Sse3,
+        Feature::Ssse3,
+        Feature::Sse41,
+        Feature::Sse42,
+        Feature::Pcid,
+        Feature::X2Apic,
+        Feature::TscDeadline,
+        Feature::Xsave,
+        Feature::Avx,
+        Feature::Rdrand,
+        Feature::Hypervisor,
+        Feature::Fsgsbase,
+        Feature::Avx2,
+        Feature::Smep,
+        Feature::Rdseed,
+        Feature::Smap,
+        Feature::Syscall,
+        Feature::Nx,
+        Feature::Pages1G,
+        Feature::Rdtscp,
+        Feature::LongMode,
+        Feature::InvariantTsc,
+    ];
+
+    // Имя в стиле /proc/cpuinfo
+    pub fn name(self) -> &'static str {
+        self.info().0
+    }
+
+    // Лист CPUID (подлист 0), регистр и номер бита
+    pub fn location(self) -> (u32, Reg, u32) {
+        let (_, leaf, reg, bit) = self.info();
+        (leaf, reg, bit)
+    }
+
+    fn info(self) -> (&'static str, u32, Reg, u32) {
+        match self {
+            Feature::Fpu => ("fpu", 1, Reg::Edx, 0),
+            Feature::Tsc => ("tsc", 1, Reg::Edx, 4),
+            Feature::Msr => ("msr", 1, Reg::Edx, 5),
+            Feature::Pae => ("pae", 1, Reg::Edx, 6),
+            Feature::Apic => ("apic", 1, Reg::Edx, 9),
+            Feature::Pge => ("pge", 1, Reg::Edx, 13),
+            Feature::Mmx => ("mmx", 1, Reg::Edx, 23),
+            Feature::Sse => ("sse", 1, Reg::Edx, 25),
+            Feature::Sse2 => ("sse2", 1, Reg::Edx, 26),
+            Feature::Htt => ("ht", 1, Reg::Edx, 28),
+            Feature::Sse3 => ("pni", 1, Reg::Ecx, 0),
+            Feature::Ssse3 => ("ssse3", 1, Reg::Ecx, 9),
+            Feature::Sse41 => ("sse4_1", 1, Reg::Ecx, 19),
+            Feature::Sse42 => ("sse4_2", 1, Reg::Ecx, 20),
+            Feature::Pcid => ("pcid", 1, Reg::Ecx, 17),
+            Feature::X2Apic => ("x2apic", 1, Reg::Ecx, 21),
+            Feature::TscDeadline => ("tsc_deadline_timer", 1, Reg::Ecx, 24),
+            Feature::Xsave => ("xsave", 1, Reg::Ecx, 26),
+            Feature::Avx => ("avx", 1, Reg::Ecx, 28),
+            Feature::Rdrand => ("rdrand", 1, Reg::Ecx, 30),
+            Feature::Hypervisor => ("hypervisor", 1, Reg::Ecx, 31),
+            Feature::Fsgsbase => ("fsgsbase", 7, Reg::Ebx, 0),
+            Feature::Avx2 => ("avx2", 7, Reg::Ebx, 5),
+            Feature::Smep => ("smep", 7, Reg::Ebx, 7),
+            Feature::Rdseed => ("rdseed", 7, Reg::Ebx, 18),
+            Feature::Smap => ("smap", 7, Reg::Ebx, 20),
+            Feature::Syscall => ("syscall", 0x8000_0001, Reg::Edx, 11),
+            Feature::Nx => ("nx", 0x8000_0001, Reg::Edx, 20),
+            Feature::Pages1G => ("pdpe1gb", 0x8000_0001, Reg::Edx, 26),
+            Feature::Rdtscp => ("rdtscp", 0x8000_0001, Reg::Edx, 27),
+            Feature::LongMode => ("lm", 0x8000_0001, Reg::Edx, 29),
+            Feature::InvariantTsc => ("constant_tsc", 0x8000_0007, Reg::Edx, 8),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{brand, decode_cache, signature, vendor, Cache, CacheKind, Feature, Regs};
+
+    #[test]
+    fn vendor_and_brand() {
+        let leaf0 = Regs {
+            eax: 0xD,
+            ebx: 0x756E_6547,
+            ecx: 0x6C65_746E,
+            edx: 0x4965_6E69,
+        };
+        assert_eq!(&vendor(leaf0), b"GenuineIntel");
+
+        let word = u32::from_le_bytes(*b"QEMU");
+        let mut leaves = [Regs::default(); 3];
+        leaves[0].eax = word;
+        leaves[2].edx = u32::from_le_bytes(*b"CPU ");
+        let brand = brand(leaves);
+        assert_eq!(&brand[..4], b"QEMU");
+        assert_eq!(&brand[44..], b"CPU ");
+    }
+
+    #[test]
+    fn family_model_stepping() {
+        // Intel Core i7-8700: семейство 6, модель 0x9E, степпинг 10
+        let s = signature(0x000906EA);
+        assert_eq!((s.family, s.model, s.stepping), (6, 0x9E, 10));
+        // AMD Ryzen (Zen 2): базовое семейство 0xF + расширенное 8
+        let s = signature(0x00870F10);
+        assert_eq!((s.family, s.model, s.stepping), (0x17, 0x71, 0));
+        // Старые процессоры без расширенных полей
+        let s = signature(0x00000543);
+        assert_eq!((s.family, s.model, s.stepping), (5, 4, 3));
+    }
+
+    #[test]
+    fn caches() {
+        // L1d: 8 путей, строка 64 байта, 64 набора, общий для 2 потоков
+        let l1d = Regs {
+            eax: 0x0000_4121,
+            ebx: 0x01C0_003F,
+            ecx: 0x0000_003F,
+            edx: 0,
+        };
+        assert_eq!(
+            decode_cache(l1d),
+            Some(Cache {
+                level: 1,
+                kind: CacheKind::Data,
+                size: 32 * 1024,
+                ways: 8,
+                line: 64,
+                sets: 64,
+                shared_by: 2,
+            })
+        );
+        assert_eq!(decode_cache(Regs::default()), None);
+    }
+
+    #[test]
+    fn feature_table() {
+        assert_eq!(Feature::X2Apic.name(), "x2apic");
+        assert_eq!(Feature::Nx.location().0, 0x8000_0001);
+        // Имена не повторяются
+        for (i, a) in Feature::ALL.iter().enumerate() {
+            for b in &Feature::ALL[i + 1..] {
+                assert_ne!(a.name(), b.name());
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+#[derive(Debug, Clone, Copy)]
+pub struct DateTime {
+    pub day: u8,
+    pub month: u8,
+    pub year: u16,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+impl DateTime {
+    pub fn update(&mut self) {
+        self.seconds += 1;
+        if self.seconds >= 60 {
+            self.seconds = 0;
+            self.minutes += 1;
+            if self.minutes >= 60 {
+                self.minutes = 0;
+                self.hours += 1;
+                if self.hours >= 24 {
+                    self.hours = 0;
+                    self.day += 1;
+                    if self.day > days_in_month(self.month, self.year) {
+                        self.day = 1;
+                        self.month += 1;
+                        if self.month > 12 {
+                            self.month = 1;
+                            self.year += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn days_in_month(month: u8, year: u16) -> u8 {
+    match month {
+        1 => 31,
+        2 => {
+            if is_leap_year(year) {
+                29
+            } else {
+                28
+            }
+        }
+        3 => 31,
+        4 => 30,
+        5 => 31,
+        6 => 30,
+        7 => 31,
+        8 => 31,
+        9 => 30,
+        10 => 31,
+        11 => 30,
+        12 => 31,
+        _ => 30,
+    }
+}
+
+// is_multiple_of нет в nightly, которым собирается ядро
+#[allow(clippy::manual_is_multiple_of)]
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateTime;
+
+    fn datetime(day: u8, month: u8, year: u16, hours: u8, minutes: u8, seconds: u8) -> DateTime {
+        DateTime {
+            day,
+            month,
+            year,
+            hours,
+            minutes,
+            seconds,
+        }
+    }
+
+    fn date(time: &DateTime) -> (u8, u8, u16, u8, u8, u8) {
+        (
+            time.day,
+            time.month,
+            time.year,
+            time.hours,
+            time.minutes,
+            time.seconds,
+        )
+    }
+
+    #[test]
+    fn seconds_roll_into_minutes_and_hours() {
+        let mut time = datetime(1, 1, 2023, 12, 59, 59);
+        time.update();
+        assert_eq!(date(&time), (1, 1, 2023, 13, 0, 0));
+    }
+
+    #[test]
+    fn end_of_month() {
+        let mut time = datetime(30, 4, 2023, 23, 59, 59);
+        time.update();
+        assert_eq!(date(&time), (1, 5, 2023, 0, 0, 0));
+    }
+
+    #[test]
+    fn end_of_year() {
+        let mut time = datetime(31, 12, 2023, 23, 59, 59);
+        time.update();
+        assert_eq!(date(&time), (1, 1, 2024, 0, 0, 0));
+    }
+
+    #[test]
+    fn leap_years() {
+        let mut time = datetime(28, 2, 2024, 23, 59, 59);
+        time.update();
+        assert_eq!(date(&time), (29, 2, 2024, 0, 0, 0));
+
+        let mut time = datetime(28, 2, 1900, 23, 59, 59);
+        time.update();
+        assert_eq!(date(&time), (1, 3, 1900, 0, 0, 0));
+
+        let mut time = datetime(28, 2, 2000, 23, 59, 59);
+        time.update();
+        assert_eq!(date(&time), (29, 2, 2000, 0, 0, 0));
+    }
+}
@@ -0,0 +1,89 @@
+pub const SCANCODE_MAP: [Option<char>; 256] = {
+    let mut map = [None; 256];
+    map[0x02] = Some('1');
+    map[0x03] = Some('2');
+    map[0x04] = Some('3');
+    map[0x05] = Some('4');
+    map[0x06] = Some('5');
+    map[0x07] = Some('6');
+    map[0x08] = Some('7');
+    map[0x09] = Some('8');
+    map[0x0A] = Some('9');
+    map[0x0B] = Some('0');
+    map[0x0C] = Some('[');
+    map[0x0D] = Some(']');
+    map[0x10] = Some('\'');
+    map[0x11] = Some(',');
+    map[0x12] = Some('.');
+    map[0x13] = Some('p');
+    map[0x14] = Some('y');
+    map[0x15] = Some('f');
+    map[0x16] = Some('g');
+    map[0x17] = Some('c');
+    map[0x18] = Some('r');
+    map[0x19] = Some('l');
+    map[0x1A] = Some('/');
+    map[0x1B] = Some('=');
+    map[0x1E] = Some('a');
+    map[0x1F] = Some('o');
+    map[0x20] = Some('e');
+    map[0x21] = Some('u');
+    map[0x22] = Some('i');
+    map[0x23] = Some('d');
+    map[0x24] = Some('h');
+    map[0x25] = Some('t');
+    map[0x26] = Some('n');
+    map[0x27] = Some('s');
+    map[0x28] = Some('-');
+    map[0x29] = Some('`');
+    map[0x2B] = Some('\\');
+    map[0x2C] = Some(';');
+    map[0x2D] = Some('q');
+    map[0x2E] = Some('j');
+    map[0x2F] = Some('k');
+    map[0x30] = Some('x');
+    map[0x31] = Some('b');
+    map[0x32] = Some('m');
+    map[0x33] = Some('w');
+    map[0x34] = Some('v');
+    map[0x35] = Some('z');
+    map[0x39] = Some(' ');
+    map[0x1C] = Some('\n');
+    map
+};
+
+// Символы при зажатом Shift
+pub const SHIFT_SCANCODE_MAP: [Option<char>; 256] = {
+    let mut map = SCANCODE_MAP;
+    map[0x02] = Some('!');
+    map[0x03] = Some('@');
+    map[0x04] = Some('#');
+    map[0x05] = Some('$');
+    map[0x06] = Some('%');
+    map[0x07] = Some('^');
+    map[0x08] = Some('&');
+    map[0x09] = Some('*');
+    map[0x0A] = Some('(');
+    map[0x0B] = Some(')');
+    map[0x0C] = Some('{');
+    map[0x0D] = Some('}');
+    map[0x10] = Some('"');
+    map[0x11] = Some('<');
+    map[0x12] = Some('>');
+    map[0x1A] = Some('?');
+    map[0x1B] = Some('+');
+    map[0x28] = Some('_');
+    map[0x29] = Some('~');
+    map[0x2B] = Some('|');
+    map[0x2C] = Some(':');
+    let mut code = 0;
+    while code < 256 {
+        if let Some(c) = SCANCODE_MAP[code] {
+            if c.is_ascii_lowercase() {
+                map[code] = Some(c.to_ascii_uppercase());
+            }
+        }
+        code += 1;
+    }
+    map
+};
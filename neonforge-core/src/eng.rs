@@ -38,8 +38,52 @@ pub const SCANCODE_MAP: [Option<char>; 256] = {
     map[0x2C] = Some('z');
     map[0x39] = Some(' ');
     map[0x1C] = Some('\n');
+    map[0x0C] = Some('-');
+    map[0x0D] = Some('=');
+    map[0x1A] = Some('[');
+    map[0x1B] = Some(']');
+    map[0x27] = Some(';');
+    map[0x28] = Some('\'');
+    map[0x29] = Some('`');
+    map[0x2B] = Some('\\');
+    map[0x33] = Some(',');
+    map[0x34] = Some('.');
+    map[0x35] = Some('/');
+    map
+};
+
+// Символы при зажатом Shift
+pub const SHIFT_SCANCODE_MAP: [Option<char>; 256] = {
+    let mut map = SCANCODE_MAP;
+    map[0x02] = Some('!');
+    map[0x03] = Some('@');
+    map[0x04] = Some('#');
+    map[0x05] = Some('$');
+    map[0x06] = Some('%');
+    map[0x07] = Some('^');
+    map[0x08] = Some('&');
+    map[0x09] = Some('*');
+    map[0x0A] = Some('(');
+    map[0x0B] = Some(')');
     map[0x0C] = Some('_');
+    map[0x0D] = Some('+');
+    map[0x1A] = Some('{');
+    map[0x1B] = Some('}');
     map[0x27] = Some(':');
-    map[0x34] = Some('.');
+    map[0x28] = Some('"');
+    map[0x29] = Some('~');
+    map[0x2B] = Some('|');
+    map[0x33] = Some('<');
+    map[0x34] = Some('>');
+    map[0x35] = Some('?');
+    let mut code = 0;
+    while code < 256 {
+        if let Some(c) = SCANCODE_MAP[code] {
+            if c.is_ascii_lowercase() {
+                map[code] = Some(c.to_ascii_uppercase());
+            }
+        }
+        code += 1;
+    }
     map
 };
@@ -0,0 +1,173 @@
+// Битовая карта физических кадров: бит установлен — кадр занят.
+// Хранилище передаёт вызывающий (в ядре — сами свободные кадры памяти).
+pub struct FrameBitmap<'a> {
+    bits: &'a mut [u64],
+    frames: usize,
+    total: usize, // Кадров, освобождённых по карте памяти
+    used: usize,
+    next: usize, // С этого кадра начинается поиск свободного
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    pub total: usize,
+    pub used: usize,
+    pub free: usize,
+}
+
+// Сколько слов нужно для карты из `frames` кадров
+pub const fn words_for(frames: usize) -> usize {
+    frames.div_ceil(64)
+}
+
+impl<'a> FrameBitmap<'a> {
+    // Все кадры изначально заняты; доступные отмечаются через `add_free`
+    pub fn new(bits: &'a mut [u64], frames: usize) -> Self {
+        assert!(bits.len() >= words_for(frames), "frame bitmap too small");
+        bits.fill(u64::MAX);
+        FrameBitmap {
+            bits,
+            frames,
+            total: 0,
+            used: 0,
+            next: 0,
+        }
+    }
+
+    fn is_used(&self, frame: usize) -> bool {
+        self.bits[frame / 64] & (1 << (frame % 64)) != 0
+    }
+
+    fn set(&mut self, frame: usize, used: bool) {
+        if used {
+            self.bits[frame / 64] |= 1 << (frame % 64);
+        } else {
+            self.bits[frame / 64] &= !(1 << (frame % 64));
+        }
+    }
+
+    // Добавляет свободные кадры [start, end) из карты памяти
+    pub fn add_free(&mut self, start: usize, end: usize) {
+        for frame in start..end.min(self.frames) {
+            if self.is_used(frame) {
+                self.set(frame, false);
+                self.total += 1;
+            }
+        }
+    }
+
+    // Занимает уже добавленные кадры [start, end) (например, под саму карту)
+    pub fn reserve(&mut self, start: usize, end: usize) {
+        for frame in start..end.min(self.frames) {
+            if !self.is_used(frame) {
+                self.set(frame, true);
+                self.used += 1;
+            }
+        }
+    }
+
+    pub fn alloc(&mut self) -> Option<usize> {
+        if self.used == self.total {
+            return None;
+        }
+        // Поиск по словам от последнего выделенного кадра, с переходом в начало
+        let words = words_for(self.frames);
+        let first = self.next / 64;
+        for i in 0..=words {
+            let word = (first + i) % words;
+            let mut free = !self.bits[word];
+            if i == 0 {
+                // Кадры до `next` в первом слове проверяются в конце обхода
+                free &= u64::MAX << (self.next % 64);
+            }
+            if free == 0 {
+                continue;
+            }
+            let frame = word * 64 + free.trailing_zeros() as usize;
+            if frame >= self.frames {
+                continue;
+            }
+            self.set(frame, true);
+            self.used += 1;
+            self.next = frame + 1;
+            return Some(frame);
+        }
+        None
+    }
+
+    // Возвращает false, если кадр не был выделен
+    pub fn free(&mut self, frame: usize) -> bool {
+        if frame >= self.frames || !self.is_used(frame) {
+            return false;
+        }
+        self.set(frame, false);
+        self.used -= 1;
+        true
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        FrameStats {
+            total: self.total,
+            used: self.used,
+            free: self.total - self.used,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{words_for, FrameBitmap, FrameStats};
+
+    #[test]
+    fn only_free_regions_are_allocated() {
+        let mut bits = [0; words_for(200)];
+        let mut frames = FrameBitmap::new(&mut bits, 200);
+        frames.add_free(10, 12);
+        frames.add_free(130, 131);
+        assert_eq!(frames.alloc(), Some(10));
+        assert_eq!(frames.alloc(), Some(11));
+        assert_eq!(frames.alloc(), Some(130));
+        assert_eq!(frames.alloc(), None);
+        assert_eq!(
+            frames.stats(),
+            FrameStats {
+                total: 3,
+                used: 3,
+                free: 0
+            }
+        );
+    }
+
+    #[test]
+    fn free_and_reuse() {
+        let mut bits = [0; words_for(64)];
+        let mut frames = FrameBitmap::new(&mut bits, 64);
+        frames.add_free(0, 64);
+        let a = frames.alloc().unwrap();
+        let b = frames.alloc().unwrap();
+        assert!(frames.free(a));
+        assert!(!frames.free(a));
+        assert_eq!(frames.stats().used, 1);
+        // После прохода до конца поиск возвращается к освобождённым кадрам
+        for _ in 0..62 {
+            assert!(frames.alloc().is_some());
+        }
+        assert_eq!(frames.alloc(), Some(a));
+        assert!(frames.alloc().is_none());
+        assert!(frames.free(b));
+    }
+
+    #[test]
+    fn reserve_and_bounds() {
+        let mut bits = [0; words_for(70)];
+        let mut frames = FrameBitmap::new(&mut bits, 70);
+        // Регионы за пределами карты обрезаются, повторное добавление не считается
+        frames.add_free(60, 100);
+        frames.add_free(60, 70);
+        frames.reserve(60, 62);
+        assert_eq!(frames.stats().total, 10);
+        assert_eq!(frames.stats().used, 2);
+        assert_eq!(frames.alloc(), Some(62));
+        assert!(!frames.free(80));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{dvorak, eng};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Delete,
+    Insert,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+pub struct Layout {
+    pub name: &'static str,
+    normal: [Option<char>; 256],
+    shift: [Option<char>; 256],
+}
+
+pub static LAYOUTS: [Layout; 2] = [
+    Layout {
+        name: "us",
+        normal: eng::SCANCODE_MAP,
+        shift: eng::SHIFT_SCANCODE_MAP,
+    },
+    Layout {
+        name: "dvorak",
+        normal: dvorak::SCANCODE_MAP,
+        shift: dvorak::SHIFT_SCANCODE_MAP,
+    },
+];
+
+pub struct Keyboard {
+    extended: bool,
+    ctrl: bool,
+    shift: bool,
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Keyboard {
+    pub const fn new() -> Self {
+        Keyboard {
+            extended: false,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    // Преобразует скан-код (набор 1) в клавишу, отслеживая префикс 0xE0 и модификаторы
+    pub fn decode(&mut self, scancode: u8, layout: &Layout) -> Option<Key> {
+        if scancode == 0xE0 {
+            self.extended = true;
+            return None;
+        }
+        let extended = self.extended;
+        self.extended = false;
+
+        match scancode {
+            0x1D => {
+                self.ctrl = true;
+                return None;
+            }
+            0x9D => {
+                self.ctrl = false;
+                return None;
+            }
+            // С префиксом 0xE0 это "фальшивый" Shift, который шлют некоторые клавиатуры
+            0x2A | 0x36 | 0xAA | 0xB6 if extended => return None,
+            0x2A | 0x36 => {
+                self.shift = true;
+                return None;
+            }
+            0xAA | 0xB6 => {
+                self.shift = false;
+                return None;
+            }
+            _ => {}
+        }
+
+        // Отпускание клавиши нас не интересует
+        if scancode & 0x80 != 0 {
+            return None;
+        }
+
+        // Клавиши навигации: с префиксом 0xE0 или на цифровом блоке при выключенном NumLock
+        let nav = match scancode {
+            0x47 => Some(Key::Home),
+            0x48 => Some(Key::Up),
+            0x4B => Some(Key::Left),
+            0x4D => Some(Key::Right),
+            0x4F => Some(Key::End),
+            0x50 => Some(Key::Down),
+            0x52 => Some(Key::Insert),
+            0x53 => Some(Key::Delete),
+            _ => None,
+        };
+        if nav.is_some() {
+            return nav;
+        }
+        if extended && scancode != 0x1C {
+            return None;
+        }
+
+        match scancode {
+            0x01 => Some(Key::Escape),
+            0x0E => Some(Key::Backspace),
+            0x0F => Some(Key::Tab),
+            0x1C => Some(Key::Enter),
+            _ if self.ctrl => layout.normal[scancode as usize].map(Key::Ctrl),
+            _ if self.shift => layout.shift[scancode as usize].map(Key::Char),
+            _ => layout.normal[scancode as usize].map(Key::Char),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Key, Keyboard, LAYOUTS};
+
+    fn decode_all(scancodes: &[u8], layout: usize) -> Vec<Key> {
+        let mut keyboard = Keyboard::new();
+        scancodes
+            .iter()
+            .filter_map(|&scancode| keyboard.decode(scancode, &LAYOUTS[layout]))
+            .collect()
+    }
+
+    #[test]
+    fn letters_and_release() {
+        // a нажата и отпущена, затем b
+        assert_eq!(
+            decode_all(&[0x1E, 0x9E, 0x30], 0),
+            [Key::Char('a'), Key::Char('b')]
+        );
+    }
+
+    #[test]
+    fn shift_and_ctrl() {
+        // Shift+a, отпускание Shift, Ctrl+c
+        assert_eq!(
+            decode_all(&[0x2A, 0x1E, 0xAA, 0x1E, 0x1D, 0x2E, 0x9D], 0),
+            [Key::Char('A'), Key::Char('a'), Key::Ctrl('c')]
+        );
+    }
+
+    #[test]
+    fn extended_keys() {
+        // Стрелки с префиксом 0xE0, Enter на цифровом блоке, "фальшивый" Shift
+        assert_eq!(
+            decode_all(&[0xE0, 0x48, 0xE0, 0x4B, 0xE0, 0x1C, 0xE0, 0x2A, 0x1E], 0),
+            [Key::Up, Key::Left, Key::Enter, Key::Char('a')]
+        );
+        assert_eq!(decode_all(&[0xE0, 0x35], 0), []);
+    }
+
+    #[test]
+    fn dvorak_layout() {
+        assert_eq!(LAYOUTS[1].name, "dvorak");
+        // Клавиша q в раскладке Dvorak даёт апостроф
+        assert_eq!(decode_all(&[0x10], 1), [Key::Char('\'')]);
+    }
+}
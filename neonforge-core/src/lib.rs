@@ -0,0 +1,23 @@
+// Логика ядра, не зависящая от оборудования: календарь, разбор аргументов,
+// раскладки клавиатуры, текстовый экран, разбор AML, учёт физических кадров,
+// классы размеров кучи, разбор конфигурационного пространства PCI и CPUID,
+// формат архива, в котором /etc сохраняется на диск, разбор скриптов оболочки.
+// Собирается и для ядра (no_std), и для хоста, где её покрывает обычный
+// `cargo test`.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod aml;
+pub mod archive;
+pub mod args;
+pub mod cpuid;
+pub mod datetime;
+mod dvorak;
+mod eng;
+pub mod frames;
+pub mod keyboard;
+pub mod pci;
+pub mod screen;
+pub mod shell;
+pub mod slab;
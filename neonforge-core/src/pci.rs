@@ -0,0 +1,224 @@
+// Разбор данных конфигурационного пространства PCI, не зависящий от способа
+// доступа к нему: BAR, названия классов устройств и возможностей.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+        wide: bool, // Занимает два соседних регистра BAR
+    },
+    Io {
+        port: u16,
+        size: u16,
+    },
+}
+
+pub fn bar_is_io(low: u32) -> bool {
+    low & 0x1 != 0
+}
+
+pub fn bar_is_64bit(low: u32) -> bool {
+    low & 0x7 == 0x4
+}
+
+// `value` — исходное содержимое BAR, `mask` — значение, прочитанное после
+// записи единиц во все биты. У 32-битного BAR старшая половина `value`
+// нулевая, а `mask` — единичная. None — BAR не реализован.
+pub fn decode_bar(value: u64, mask: u64) -> Option<Bar> {
+    let low = value as u32;
+    if bar_is_io(low) {
+        if mask as u32 & !0x3 == 0 {
+            return None;
+        }
+        // Порты 16-битные, старшие биты маски могут читаться нулями
+        let size = !((mask as u32 | 0xFFFF_0000) & !0x3) as u16;
+        return Some(Bar::Io {
+            port: (low & !0x3) as u16,
+            size: size.wrapping_add(1),
+        });
+    }
+    let mask = mask & !0xF;
+    let wide = bar_is_64bit(low);
+    if (if wide { mask } else { mask & 0xFFFF_FFFF }) == 0 {
+        return None;
+    }
+    Some(Bar::Memory {
+        address: value & !0xF,
+        size: (!mask).wrapping_add(1),
+        prefetchable: low & 0x8 != 0,
+        wide,
+    })
+}
+
+// Название класса устройства в духе lspci
+pub fn class_name(class: u8, subclass: u8, prog_if: u8) -> &'static str {
+    match (class, subclass, prog_if) {
+        (0x00, 0x01, _) => "VGA compatible unclassified device",
+        (0x00, _, _) => "Unclassified device",
+        (0x01, 0x00, _) => "SCSI storage controller",
+        (0x01, 0x01, _) => "IDE interface",
+        (0x01, 0x02, _) => "Floppy disk controller",
+        (0x01, 0x04, _) => "RAID bus controller",
+        (0x01, 0x05, _) => "ATA controller",
+        (0x01, 0x06, 0x01) => "SATA controller (AHCI)",
+        (0x01, 0x06, _) => "SATA controller",
+        (0x01, 0x07, _) => "Serial Attached SCSI controller",
+        (0x01, 0x08, 0x02) => "Non-Volatile memory controller (NVMe)",
+        (0x01, 0x08, _) => "Non-Volatile memory controller",
+        (0x01, _, _) => "Mass storage controller",
+        (0x02, 0x00, _) => "Ethernet controller",
+        (0x02, 0x80, _) => "Network controller",
+        (0x02, _, _) => "Network controller",
+        (0x03, 0x00, _) => "VGA compatible controller",
+        (0x03, 0x01, _) => "XGA compatible controller",
+        (0x03, 0x02, _) => "3D controller",
+        (0x03, _, _) => "Display controller",
+        (0x04, 0x00, _) => "Multimedia video controller",
+        (0x04, 0x01, _) => "Multimedia audio controller",
+        (0x04, 0x03, _) => "Audio device",
+        (0x04, _, _) => "Multimedia controller",
+        (0x05, 0x00, _) => "RAM memory",
+        (0x05, _, _) => "Memory controller",
+        (0x06, 0x00, _) => "Host bridge",
+        (0x06, 0x01, _) => "ISA bridge",
+        (0x06, 0x04, _) => "PCI bridge",
+        (0x06, 0x07, _) => "CardBus bridge",
+        (0x06, _, _) => "Bridge",
+        (0x07, 0x00, _) => "Serial controller",
+        (0x07, 0x01, _) => "Parallel controller",
+        (0x07, _, _) => "Communication controller",
+        (0x08, 0x00, _) => "PIC",
+        (0x08, 0x01, _) => "DMA controller",
+        (0x08, 0x02, _) => "Timer",
+        (0x08, 0x03, _) => "RTC",
+        (0x08, 0x05, _) => "SD Host controller",
+        (0x08, 0x06, _) => "IOMMU",
+        (0x08, _, _) => "System peripheral",
+        (0x09, _, _) => "Input device controller",
+        (0x0A, _, _) => "Docking station",
+        (0x0B, _, _) => "Processor",
+        (0x0C, 0x03, 0x00) => "USB controller (UHCI)",
+        (0x0C, 0x03, 0x10) => "USB controller (OHCI)",
+        (0x0C, 0x03, 0x20) => "USB controller (EHCI)",
+        (0x0C, 0x03, 0x30) => "USB controller (xHCI)",
+        (0x0C, 0x03, _) => "USB controller",
+        (0x0C, 0x05, _) => "SMBus",
+        (0x0C, _, _) => "Serial bus controller",
+        (0x0D, _, _) => "Wireless controller",
+        (0x0E, _, _) => "Intelligent controller",
+        (0x0F, _, _) => "Satellite communications controller",
+        (0x10, _, _) => "Encryption controller",
+        (0x11, _, _) => "Signal processing controller",
+        (0x12, _, _) => "Processing accelerators",
+        (0x13, _, _) => "Non-Essential Instrumentation",
+        (0x40, _, _) => "Coprocessor",
+        (0xFF, _, _) => "Unassigned class",
+        _ => "Unknown class",
+    }
+}
+
+// Производители, которые встречаются в эмуляторах и типичных ПК
+pub fn vendor_name(id: u16) -> Option<&'static str> {
+    Some(match id {
+        0x1002 => "AMD/ATI",
+        0x1022 => "AMD",
+        0x106B => "Apple",
+        0x10DE => "NVIDIA",
+        0x10EC => "Realtek",
+        0x1234 => "QEMU",
+        0x14E4 => "Broadcom",
+        0x15AD => "VMware",
+        0x168C => "Qualcomm Atheros",
+        0x1AF4 => "Red Hat (virtio)",
+        0x1B36 => "Red Hat",
+        0x1B21 => "ASMedia",
+        0x144D => "Samsung",
+        0x80EE => "VirtualBox",
+        0x8086 => "Intel",
+        _ => return None,
+    })
+}
+
+pub fn capability_name(id: u8) -> &'static str {
+    match id {
+        0x01 => "Power Management",
+        0x02 => "AGP",
+        0x03 => "Vital Product Data",
+        0x04 => "Slot Identification",
+        0x05 => "MSI",
+        0x06 => "CompactPCI Hot Swap",
+        0x07 => "PCI-X",
+        0x08 => "HyperTransport",
+        0x09 => "Vendor Specific",
+        0x0A => "Debug port",
+        0x0B => "CompactPCI Central Resource Control",
+        0x0C => "PCI Hot-Plug",
+        0x0D => "Bridge Subsystem Vendor ID",
+        0x0E => "AGP 8x",
+        0x0F => "Secure Device",
+        0x10 => "PCI Express",
+        0x11 => "MSI-X",
+        0x12 => "SATA Data/Index Configuration",
+        0x13 => "Advanced Features",
+        0x14 => "Enhanced Allocation",
+        0x15 => "Flattening Portal Bridge",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bar_is_64bit, capability_name, class_name, decode_bar, vendor_name, Bar};
+
+    #[test]
+    fn memory_bars() {
+        // 32-битный BAR размером 1 MiB
+        assert_eq!(
+            decode_bar(0xFEB0_0000, 0xFFFF_FFFF_FFF0_0000),
+            Some(Bar::Memory {
+                address: 0xFEB0_0000,
+                size: 0x10_0000,
+                prefetchable: false,
+                wide: false
+            })
+        );
+        // 64-битный предвыбираемый BAR размером 16 KiB
+        assert!(bar_is_64bit(0xFE00_000C));
+        assert_eq!(
+            decode_bar(0x0000_0080_FE00_000C, 0xFFFF_FFFF_FFFF_C00C),
+            Some(Bar::Memory {
+                address: 0x80_FE00_0000,
+                size: 0x4000,
+                prefetchable: true,
+                wide: true
+            })
+        );
+        assert_eq!(decode_bar(0, 0xFFFF_FFFF_0000_0000), None);
+    }
+
+    #[test]
+    fn io_bars() {
+        let bar = Bar::Io {
+            port: 0xC000,
+            size: 0x20,
+        };
+        assert_eq!(decode_bar(0xC001, 0xFFFF_FFFF_FFFF_FFE1), Some(bar));
+        // Старшие биты маски портов прочитаны нулями
+        assert_eq!(decode_bar(0xC001, 0xFFFF_FFFF_0000_FFE1), Some(bar));
+        assert_eq!(decode_bar(0x0001, 0xFFFF_FFFF_0000_0001), None);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(class_name(0x06, 0x00, 0x00), "Host bridge");
+        assert_eq!(class_name(0x01, 0x06, 0x01), "SATA controller (AHCI)");
+        assert_eq!(class_name(0x0C, 0x03, 0x30), "USB controller (xHCI)");
+        assert_eq!(class_name(0x02, 0x42, 0x00), "Network controller");
+        assert_eq!(class_name(0x77, 0x00, 0x00), "Unknown class");
+        assert_eq!(capability_name(0x11), "MSI-X");
+        assert_eq!(vendor_name(0x8086), Some("Intel"));
+        assert_eq!(vendor_name(0xFFFF), None);
+    }
+}
@@ -0,0 +1,161 @@
+pub const ROWS: usize = 25;
+pub const COLS: usize = 80;
+pub const TEXT_ROWS: usize = ROWS - 1; // Последняя строка занята панелью состояния
+pub const TAB_WIDTH: usize = 4;
+
+// Символы или цвета всего экрана
+pub type Grid = [[u8; COLS]; ROWS];
+
+// Куда выводится изменённое содержимое (в ядре — видеопамять VGA)
+pub trait Display {
+    fn draw(&mut self, row: usize, col: usize, byte: u8, color: u8);
+    // Экран прокручен: нужно перерисовать его целиком
+    fn redraw(&mut self, chars: &Grid, colors: &Grid);
+}
+
+// Сдвигает строки на одну вверх, последняя строка очищается
+pub fn scroll(grid: &mut Grid) {
+    grid.copy_within(1.., 0);
+    grid[ROWS - 1] = [0; COLS];
+}
+
+// Вывод текста в экранный буфер с позиции курсора: перенос длинных строк,
+// '\n', '\r', '\t' и прокрутка. Символы вне ASCII отображаются как '?'.
+pub struct Terminal<'a, D: Display> {
+    pub chars: &'a mut Grid,
+    pub colors: &'a mut Grid,
+    pub row: usize,
+    pub col: usize,
+    pub display: D,
+}
+
+impl<D: Display> Terminal<'_, D> {
+    pub fn write_byte(&mut self, byte: u8, color: u8) {
+        match byte {
+            b'\n' => self.new_line(),
+            b'\r' => self.col = 0,
+            b'\t' => {
+                for _ in 0..TAB_WIDTH - self.col % TAB_WIDTH {
+                    self.write_byte(b' ', color);
+                }
+            }
+            byte => {
+                if self.col >= COLS {
+                    self.new_line();
+                }
+                let byte = if byte.is_ascii() { byte } else { b'?' };
+                self.chars[self.row][self.col] = byte;
+                self.colors[self.row][self.col] = color;
+                self.display.draw(self.row, self.col, byte, color);
+                self.col += 1;
+            }
+        }
+    }
+
+    pub fn write_str(&mut self, s: &str, color: u8) {
+        for byte in s.bytes() {
+            self.write_byte(byte, color);
+        }
+    }
+
+    pub fn new_line(&mut self) {
+        self.col = 0;
+        if self.row + 1 >= TEXT_ROWS {
+            self.scroll();
+        } else {
+            self.row += 1;
+        }
+    }
+
+    pub fn scroll(&mut self) {
+        scroll(self.chars);
+        scroll(self.colors);
+        self.display.redraw(self.chars, self.colors);
+    }
+
+    pub fn clear(&mut self) {
+        *self.chars = [[0; COLS]; ROWS];
+        *self.colors = [[0; COLS]; ROWS];
+        self.row = 0;
+        self.col = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Display, Grid, Terminal, COLS, ROWS, TEXT_ROWS};
+
+    #[derive(Default)]
+    struct Counter {
+        draws: usize,
+        redraws: usize,
+    }
+
+    impl Display for Counter {
+        fn draw(&mut self, _row: usize, _col: usize, _byte: u8, _color: u8) {
+            self.draws += 1;
+        }
+
+        fn redraw(&mut self, _chars: &Grid, _colors: &Grid) {
+            self.redraws += 1;
+        }
+    }
+
+    fn line(chars: &Grid, row: usize) -> String {
+        chars[row]
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| byte as char)
+            .collect()
+    }
+
+    fn with_terminal(f: impl FnOnce(&mut Terminal<Counter>)) -> (Grid, Grid) {
+        let mut chars = [[0; COLS]; ROWS];
+        let mut colors = [[0; COLS]; ROWS];
+        let mut terminal = Terminal {
+            chars: &mut chars,
+            colors: &mut colors,
+            row: 0,
+            col: 0,
+            display: Counter::default(),
+        };
+        f(&mut terminal);
+        (chars, colors)
+    }
+
+    #[test]
+    fn text_and_control_characters() {
+        let (chars, colors) = with_terminal(|t| {
+            t.write_str("hello\nab\rX\tY é", 0x07);
+            assert_eq!((t.row, t.col), (1, 8));
+        });
+        assert_eq!(line(&chars, 0), "hello");
+        assert_eq!(line(&chars, 1), "X   Y ??");
+        assert_eq!(colors[0][0], 0x07);
+    }
+
+    #[test]
+    fn long_lines_wrap() {
+        let (chars, _) = with_terminal(|t| {
+            t.write_str(&"x".repeat(COLS + 3), 0x07);
+            assert_eq!((t.row, t.col), (1, 3));
+            assert_eq!(t.display.draws, COLS + 3);
+        });
+        assert_eq!(line(&chars, 1), "xxx");
+    }
+
+    #[test]
+    fn scrolls_above_status_bar() {
+        let (chars, _) = with_terminal(|t| {
+            for i in 0..TEXT_ROWS + 2 {
+                t.write_str(&format!("{}\n", i), 0x07);
+            }
+            assert_eq!(t.row, TEXT_ROWS - 1);
+            assert_eq!(t.display.redraws, 3);
+        });
+        assert_eq!(line(&chars, 0), "3");
+        assert_eq!(line(&chars, TEXT_ROWS - 2), "25");
+        assert_eq!(line(&chars, TEXT_ROWS - 1), "");
+        assert_eq!(line(&chars, ROWS - 1), "");
+    }
+}
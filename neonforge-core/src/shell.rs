@@ -0,0 +1,673 @@
+// Разбор командного языка оболочки: лексер и парсер скриптов. Выполнение,
+// переменные и таблица псевдонимов остаются в ядре.
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::Peekable;
+use core::str::Chars;
+
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Разбирает `NAME=value`
+pub fn assignment(word: &str) -> Option<(&str, &str)> {
+    word.split_once('=').filter(|(name, _)| is_name(name))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Var(String), // $NAME, ${NAME}, $?, $#, $1
+}
+
+// Слово до подстановки переменных
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Word {
+    parts: Vec<Part>,
+    pub quoted: bool,
+}
+
+impl Word {
+    fn push(&mut self, c: char) {
+        match self.parts.last_mut() {
+            Some(Part::Text(text)) => text.push(c),
+            _ => self.parts.push(Part::Text(String::from(c))),
+        }
+    }
+
+    // Ключевые слова (`if`, `done`, ...) распознаются только без кавычек и `$`
+    pub fn keyword(&self) -> Option<&str> {
+        match &self.parts[..] {
+            [Part::Text(text)] if !self.quoted => Some(text),
+            _ => None,
+        }
+    }
+
+    // Подставляет значения переменных; `lookup` получает имя без `$`
+    pub fn expand(&self, lookup: &dyn Fn(&str) -> String) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => result.push_str(text),
+                Part::Var(name) => result.push_str(&lookup(name)),
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(Word),
+    Pipe,    // |
+    And,     // &&
+    Or,      // ||
+    Semi,    // ;
+    Newline, // \n
+    Out,     // >
+    Append,  // >>
+    In,      // <
+}
+
+// Разбирает `$` в начале подстановки; если за ним нет имени, это обычный символ
+fn lex_var(chars: &mut Peekable<Chars>, word: &mut Word) -> Result<(), String> {
+    match chars.peek().copied() {
+        Some(c @ ('?' | '#' | '0'..='9')) => {
+            chars.next();
+            word.parts.push(Part::Var(String::from(c)));
+        }
+        Some('{') => {
+            chars.next();
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(String::from("unterminated ${")),
+                }
+            }
+            if !is_name(&name) && name != "?" && name != "#" && name.parse::<usize>().is_err() {
+                return Err(format!("bad substitution: ${{{}}}", name));
+            }
+            word.parts.push(Part::Var(name));
+        }
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_alphanumeric() && c != '_' {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            word.parts.push(Part::Var(name));
+        }
+        _ => word.push('$'),
+    }
+    Ok(())
+}
+
+// Разбивает текст на слова и операторы. Поддерживаются одинарные кавычки
+// (без экранирования и подстановок внутри), двойные кавычки, обратная косая
+// черта, `$VAR` и комментарии от `#` до конца строки.
+fn lex(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut word = Word::default();
+    let mut in_word = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let operator = match c {
+            '|' if chars.peek() == Some(&'|') => Some(Token::Or),
+            '|' => Some(Token::Pipe),
+            '&' if chars.peek() == Some(&'&') => Some(Token::And),
+            '&' => return Err(String::from("background jobs are not supported")),
+            ';' => Some(Token::Semi),
+            '\n' => Some(Token::Newline),
+            '>' if chars.peek() == Some(&'>') => Some(Token::Append),
+            '>' => Some(Token::Out),
+            '<' => Some(Token::In),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            if matches!(operator, Token::Or | Token::And | Token::Append) {
+                chars.next();
+            }
+            if in_word {
+                tokens.push(Token::Word(core::mem::take(&mut word)));
+                in_word = false;
+            }
+            tokens.push(operator);
+            continue;
+        }
+
+        match c {
+            ' ' | '\t' | '\r' => {
+                if in_word {
+                    tokens.push(Token::Word(core::mem::take(&mut word)));
+                    in_word = false;
+                }
+            }
+            '#' if !in_word => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '\'' => {
+                in_word = true;
+                word.quoted = true;
+                word.parts.push(Part::Text(String::new()));
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("unterminated quote")),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                word.quoted = true;
+                word.parts.push(Part::Text(String::new()));
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(String::from("unterminated quote")),
+                        },
+                        Some('$') => lex_var(&mut chars, &mut word)?,
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("unterminated quote")),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                word.quoted = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err(String::from("trailing backslash")),
+                }
+            }
+            '$' => {
+                in_word = true;
+                lex_var(&mut chars, &mut word)?;
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
+
+pub struct Redirect {
+    pub path: Word,
+    pub append: bool,
+}
+
+#[derive(Default)]
+pub struct SimpleCommand {
+    pub argv: Vec<Word>,
+    pub input: Option<Word>,
+    pub output: Option<Redirect>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    Always, // ; или перевод строки
+    And,    // &&
+    Or,     // ||
+}
+
+pub enum Node {
+    Pipeline(Vec<SimpleCommand>),
+    If {
+        branches: Vec<(List, List)>, // условие и тело для if и каждого elif
+        otherwise: Option<List>,
+    },
+    While {
+        condition: List,
+        body: List,
+    },
+    For {
+        name: String,
+        items: Vec<Word>,
+        body: List,
+    },
+}
+
+pub type List = Vec<(Connector, Node)>;
+
+fn syntax_error(token: Option<&Token>) -> String {
+    let text = match token {
+        None => "end of line",
+        Some(Token::Word(word)) => word.keyword().unwrap_or("word"),
+        Some(Token::Pipe) => "|",
+        Some(Token::And) => "&&",
+        Some(Token::Or) => "||",
+        Some(Token::Semi) => ";",
+        Some(Token::Newline) => "newline",
+        Some(Token::Out) => ">",
+        Some(Token::Append) => ">>",
+        Some(Token::In) => "<",
+    };
+    format!("syntax error near `{}`", text)
+}
+
+// list     := command (('&&' | '||' | ';' | '\n') command)*
+// command  := if | while | for | pipeline
+// if       := 'if' list 'then' list ('elif' list 'then' list)* ['else' list] 'fi'
+// while    := 'while' list 'do' list 'done'
+// for      := 'for' NAME 'in' WORD* (';' | '\n') 'do' list 'done'
+// pipeline := simple ('|' simple)*
+// simple   := (WORD | ('<' | '>' | '>>') WORD)+
+pub struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    aliases: &'a dyn Fn(&str) -> Option<String>, // Значение псевдонима по имени
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(text: &str, aliases: &'a dyn Fn(&str) -> Option<String>) -> Result<Self, String> {
+        Ok(Parser {
+            tokens: lex(text)?,
+            pos: 0,
+            aliases,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => word.keyword(),
+            _ => None,
+        }
+    }
+
+    fn error(&self) -> String {
+        syntax_error(self.peek())
+    }
+
+    fn expect(&mut self, keyword: &str) -> Result<(), String> {
+        if self.peek_keyword() != Some(keyword) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(Token::Semi | Token::Newline)) {
+            self.pos += 1;
+        }
+    }
+
+    // Разбирает команды до конца текста или до одного из `terminators`
+    fn list(&mut self, terminators: &[&str]) -> Result<List, String> {
+        let mut list = List::new();
+        let mut connector = Connector::Always;
+        loop {
+            // После && и || допустим только перевод строки
+            if connector == Connector::Always {
+                self.skip_separators();
+            } else {
+                self.skip_newlines();
+            }
+            let at_end = match self.peek_keyword() {
+                Some(keyword) => terminators.contains(&keyword),
+                None => self.peek().is_none(),
+            };
+            if at_end {
+                if connector != Connector::Always {
+                    return Err(self.error());
+                }
+                return Ok(list);
+            }
+
+            list.push((connector, self.command()?));
+
+            connector = match self.peek() {
+                Some(Token::And) => Connector::And,
+                Some(Token::Or) => Connector::Or,
+                Some(Token::Semi | Token::Newline) | None => Connector::Always,
+                Some(Token::Word(_))
+                    if self
+                        .peek_keyword()
+                        .is_some_and(|keyword| terminators.contains(&keyword)) =>
+                {
+                    Connector::Always
+                }
+                Some(_) => return Err(self.error()),
+            };
+            if connector != Connector::Always {
+                self.pos += 1;
+            }
+        }
+    }
+
+    // Следующая команда верхнего уровня вместе с цепочкой `&&` и `||`;
+    // None в конце текста. Псевдонимы раскрываются при разборе, поэтому
+    // вызывающий выполняет каждую команду до разбора следующей: `alias`
+    // действует на следующие строки скрипта.
+    pub fn next_command(&mut self) -> Result<Option<List>, String> {
+        self.skip_separators();
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        let mut list = List::new();
+        let mut connector = Connector::Always;
+        loop {
+            list.push((connector, self.command()?));
+            connector = match self.peek() {
+                Some(Token::And) => Connector::And,
+                Some(Token::Or) => Connector::Or,
+                Some(Token::Semi | Token::Newline) | None => return Ok(Some(list)),
+                Some(_) => return Err(self.error()),
+            };
+            self.pos += 1;
+            self.skip_newlines();
+            if self.peek().is_none() {
+                return Err(self.error());
+            }
+        }
+    }
+
+    // Список, который не может быть пустым (условие if/while)
+    fn condition(&mut self, terminator: &str) -> Result<List, String> {
+        let list = self.list(&[terminator])?;
+        if list.is_empty() {
+            return Err(self.error());
+        }
+        Ok(list)
+    }
+
+    // Подставляет псевдоним вместо первого слова команды. Псевдоним может
+    // ссылаться на другой, но не на себя: `alias ls='ls -a'`.
+    fn expand_aliases(&mut self) -> Result<(), String> {
+        let mut expanded: Vec<String> = Vec::new();
+        while let Some(name) = self.peek_keyword().map(String::from) {
+            if expanded.contains(&name) {
+                break;
+            }
+            let Some(value) = (self.aliases)(&name) else {
+                break;
+            };
+            let tokens = lex(&value)?;
+            self.tokens.splice(self.pos..self.pos + 1, tokens);
+            expanded.push(name);
+        }
+        Ok(())
+    }
+
+    fn command(&mut self) -> Result<Node, String> {
+        self.expand_aliases()?;
+        match self.peek_keyword() {
+            Some("if") => self.if_clause(),
+            Some("while") => self.while_clause(),
+            Some("for") => self.for_clause(),
+            Some("then" | "elif" | "else" | "fi" | "do" | "done") => Err(self.error()),
+            _ => self.pipeline(),
+        }
+    }
+
+    fn if_clause(&mut self) -> Result<Node, String> {
+        self.pos += 1;
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.condition("then")?;
+            self.expect("then")?;
+            let body = self.list(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+            if self.peek_keyword() != Some("elif") {
+                break;
+            }
+            self.pos += 1;
+        }
+        let otherwise = if self.peek_keyword() == Some("else") {
+            self.pos += 1;
+            Some(self.list(&["fi"])?)
+        } else {
+            None
+        };
+        self.expect("fi")?;
+        Ok(Node::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn while_clause(&mut self) -> Result<Node, String> {
+        self.pos += 1;
+        let condition = self.condition("do")?;
+        self.expect("do")?;
+        let body = self.list(&["done"])?;
+        self.expect("done")?;
+        Ok(Node::While { condition, body })
+    }
+
+    fn for_clause(&mut self) -> Result<Node, String> {
+        self.pos += 1;
+        let name = match self.peek_keyword() {
+            Some(name) if is_name(name) => String::from(name),
+            _ => return Err(self.error()),
+        };
+        self.pos += 1;
+        self.expect("in")?;
+
+        let mut items = Vec::new();
+        while let Some(Token::Word(word)) = self.peek() {
+            items.push(word.clone());
+            self.pos += 1;
+        }
+        if !matches!(self.peek(), Some(Token::Semi | Token::Newline)) {
+            return Err(self.error());
+        }
+        self.skip_separators();
+        self.expect("do")?;
+        let body = self.list(&["done"])?;
+        self.expect("done")?;
+        Ok(Node::For { name, items, body })
+    }
+
+    fn pipeline(&mut self) -> Result<Node, String> {
+        let mut commands = vec![self.simple()?];
+        while self.peek() == Some(&Token::Pipe) {
+            self.pos += 1;
+            self.skip_newlines();
+            self.expand_aliases()?;
+            commands.push(self.simple()?);
+        }
+        Ok(Node::Pipeline(commands))
+    }
+
+    fn simple(&mut self) -> Result<SimpleCommand, String> {
+        let mut command = SimpleCommand::default();
+        loop {
+            match self.peek() {
+                Some(Token::Word(word)) => {
+                    command.argv.push(word.clone());
+                    self.pos += 1;
+                }
+                Some(Token::Out | Token::Append | Token::In) => {
+                    let operator = self.next();
+                    let path = match self.next() {
+                        Some(Token::Word(path)) => path,
+                        token => return Err(syntax_error(token.as_ref())),
+                    };
+                    match operator {
+                        Some(Token::In) => command.input = Some(path),
+                        _ => {
+                            command.output = Some(Redirect {
+                                path,
+                                append: operator == Some(Token::Append),
+                            })
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        if command.argv.is_empty() && command.input.is_none() && command.output.is_none() {
+            return Err(self.error());
+        }
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connector, List, Node, Parser};
+    use alloc::collections::BTreeMap;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    fn no_aliases(_: &str) -> Option<String> {
+        None
+    }
+
+    fn parse(text: &str) -> Result<Vec<List>, String> {
+        let mut parser = Parser::new(text, &no_aliases)?;
+        let mut commands = Vec::new();
+        while let Some(list) = parser.next_command()? {
+            commands.push(list);
+        }
+        Ok(commands)
+    }
+
+    fn vars(name: &str) -> String {
+        match name {
+            "NAME" => String::from("world"),
+            "1" => String::from("first"),
+            _ => String::new(),
+        }
+    }
+
+    // Слова первой команды конвейера после подстановки переменных
+    fn argv(list: &List) -> Vec<String> {
+        match &list[0].1 {
+            Node::Pipeline(commands) => commands[0]
+                .argv
+                .iter()
+                .map(|word| word.expand(&vars))
+                .collect(),
+            _ => panic!("not a pipeline"),
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(parse("echo |").is_err());
+        assert!(parse("| echo").is_err());
+        assert!(parse("echo 'unterminated").is_err());
+        assert!(parse("if true; then echo x").is_err());
+        assert!(parse("for x in a; echo $x; done").is_err());
+        assert!(parse("echo >").is_err());
+        assert!(parse("echo &&").is_err());
+        assert!(parse("fi").is_err());
+    }
+
+    #[test]
+    fn words_and_quotes() {
+        let commands = parse("echo \"hello $NAME\" '$NAME' a\\ b ${1}x # comment").unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(
+            argv(&commands[0]),
+            ["echo", "hello world", "$NAME", "a b", "firstx"]
+        );
+        assert!(parse("echo ${1x}").is_err());
+    }
+
+    #[test]
+    fn commands_and_blocks() {
+        let commands = parse("a && b || c; d\n\ne | f > out").unwrap();
+        assert_eq!(commands.len(), 3);
+        let connectors: Vec<Connector> = commands[0].iter().map(|(c, _)| *c).collect();
+        assert_eq!(
+            connectors,
+            [Connector::Always, Connector::And, Connector::Or]
+        );
+        match &commands[2][0].1 {
+            Node::Pipeline(pipeline) => {
+                assert_eq!(pipeline.len(), 2);
+                assert!(pipeline[1].output.as_ref().is_some_and(|r| !r.append));
+            }
+            _ => panic!("not a pipeline"),
+        }
+
+        let commands =
+            parse("if a; then b; elif c; then d; else e; fi; for x in 1 2; do f; done").unwrap();
+        assert!(matches!(
+            &commands[0][0].1,
+            Node::If { branches, otherwise: Some(_) } if branches.len() == 2
+        ));
+        assert!(matches!(
+            &commands[1][0].1,
+            Node::For { name, items, .. } if name == "x" && items.len() == 2
+        ));
+    }
+
+    #[test]
+    fn aliases() {
+        let aliases = |name: &str| match name {
+            "ll" => Some(String::from("ls -a")),
+            "ls" => Some(String::from("ls --color")), // Ссылается на себя
+            _ => None,
+        };
+        let mut parser = Parser::new("ll /tmp | ll", &aliases).unwrap();
+        let list = parser.next_command().unwrap().unwrap();
+        assert_eq!(argv(&list), ["ls", "--color", "-a", "/tmp"]);
+        // Раскрываются только первые слова команд
+        let mut parser = Parser::new("echo ll", &aliases).unwrap();
+        assert_eq!(
+            argv(&parser.next_command().unwrap().unwrap()),
+            ["echo", "ll"]
+        );
+    }
+
+    #[test]
+    fn aliases_looked_up_per_command() {
+        // Псевдоним, заданный после разбора первой команды, действует на вторую
+        let table = RefCell::new(BTreeMap::new());
+        let aliases = |name: &str| table.borrow().get(name).cloned();
+        let mut parser = Parser::new("greet\ngreet", &aliases).unwrap();
+        let first = parser.next_command().unwrap().unwrap();
+        assert_eq!(argv(&first), ["greet"]);
+        table
+            .borrow_mut()
+            .insert(String::from("greet"), String::from("echo hi"));
+        let second = parser.next_command().unwrap().unwrap();
+        assert_eq!(argv(&second), ["echo", "hi"]);
+        assert!(parser.next_command().unwrap().is_none());
+    }
+}
@@ -0,0 +1,72 @@
+// Классы размеров распределителя блоков фиксированного размера. Блок выровнен
+// на свой размер, поэтому подходит для любого выравнивания не больше размера.
+pub const CLASSES: usize = 9;
+pub const BLOCK_SIZES: [usize; CLASSES] = [8, 16, 32, 64, 128, 256, 512, 1024, 2048];
+
+// Наименьший класс для размещения; None — размещение идёт в общую кучу
+pub fn class_index(size: usize, align: usize) -> Option<usize> {
+    let needed = size.max(align);
+    BLOCK_SIZES.iter().position(|&block| block >= needed)
+}
+
+// Счётчики одного класса размеров
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassStats {
+    pub allocs: u64,
+    pub frees: u64,
+    pub peak: u64, // Наибольшее число одновременно занятых блоков
+}
+
+impl ClassStats {
+    pub const fn new() -> Self {
+        ClassStats {
+            allocs: 0,
+            frees: 0,
+            peak: 0,
+        }
+    }
+
+    pub fn live(&self) -> u64 {
+        self.allocs - self.frees
+    }
+
+    pub fn record_alloc(&mut self) {
+        self.allocs += 1;
+        self.peak = self.peak.max(self.live());
+    }
+
+    pub fn record_free(&mut self) {
+        self.frees += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{class_index, ClassStats, BLOCK_SIZES};
+
+    #[test]
+    fn size_classes() {
+        assert_eq!(class_index(1, 1), Some(0));
+        assert_eq!(class_index(8, 8), Some(0));
+        assert_eq!(class_index(9, 1), Some(1));
+        assert_eq!(class_index(24, 8), Some(2));
+        // Выравнивание больше размера требует блока побольше
+        assert_eq!(class_index(8, 64), Some(3));
+        assert_eq!(class_index(2048, 8), Some(BLOCK_SIZES.len() - 1));
+        assert_eq!(class_index(2049, 8), None);
+        assert_eq!(class_index(16, 4096), None);
+    }
+
+    #[test]
+    fn peak_usage() {
+        let mut stats = ClassStats::new();
+        stats.record_alloc();
+        stats.record_alloc();
+        stats.record_free();
+        stats.record_alloc();
+        assert_eq!(stats.live(), 2);
+        assert_eq!(stats.peak, 2);
+        stats.record_alloc();
+        assert_eq!(stats.peak, 3);
+    }
+}
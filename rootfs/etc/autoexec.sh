@@ -0,0 +1,7 @@
+# Выполняется при загрузке, перед первым приглашением.
+# Файл копируется в /etc/autoexec.sh RAM-диска при сборке ядра.
+
+export HOSTNAME=neonforge
+set GREETING="Welcome to NeonForge"
+
+echo $GREETING
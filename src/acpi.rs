@@ -0,0 +1,635 @@
+use crate::commands::{register, Command, Io};
+use crate::memory::read_phys;
+use crate::pit::{sleep_ms, wait_while};
+use crate::{eprintln, log};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use neonforge_core::aml::parse_s5;
+use neonforge_core::args::{ArgSpec, Args};
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+const SDT_HEADER_SIZE: u64 = 36;
+
+// Биты регистра PM1_CNT
+const SCI_EN: u16 = 1 << 0;
+const SLP_TYP_SHIFT: u16 = 10;
+const SLP_TYP_MASK: u16 = 0x7 << SLP_TYP_SHIFT;
+const SLP_EN: u16 = 1 << 13;
+
+const RESET_REG_SUP: u32 = 1 << 10; // Флаг FADT: регистр сброса поддерживается
+
+// Порты выключения эмуляторов: (порт, значение)
+const EMULATOR_SHUTDOWN_PORTS: [(u16, u16); 3] = [
+    (0x604, 0x2000),  // QEMU
+    (0xB004, 0x2000), // Bochs и старые версии QEMU
+    (0x4004, 0x3400), // VirtualBox
+];
+const SHUTDOWN_TIMEOUT_MS: usize = 500;
+
+// Generic Address Structure: регистр в памяти или в пространстве портов
+#[derive(Debug, Clone, Copy)]
+pub struct GenericAddress {
+    pub space: u8, // 0 — память, 1 — порты ввода-вывода
+    pub address: u64,
+}
+
+impl GenericAddress {
+    unsafe fn read(addr: u64) -> Self {
+        GenericAddress {
+            space: read_phys(addr),
+            address: read_phys(addr + 4),
+        }
+    }
+}
+
+// Заголовок таблицы из RSDT/XSDT
+#[derive(Debug, Clone, Copy)]
+pub struct Table {
+    pub signature: [u8; 4],
+    pub address: u64,
+    pub length: u32,
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub checksum_ok: bool,
+}
+
+// Поля FADT, нужные для выключения и перезагрузки
+#[derive(Debug, Clone, Copy)]
+pub struct Fadt {
+    pub dsdt: u64,
+    pub smi_cmd: u32,
+    pub acpi_enable: u8,
+    pub pm1a_cnt: u16,
+    pub pm1b_cnt: u16,
+    pub reset: Option<(GenericAddress, u8)>, // Регистр сброса и значение для записи
+}
+
+pub struct Acpi {
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    pub tables: Vec<Table>,
+    pub fadt: Option<Fadt>,
+    pub s5: Option<(u16, u16)>, // SLP_TYPa и SLP_TYPb из \_S5
+}
+
+impl Acpi {
+    pub fn find(&self, signature: &[u8; 4]) -> Option<&Table> {
+        self.tables
+            .iter()
+            .find(|table| &table.signature == signature)
+    }
+}
+
+pub static ACPI: Mutex<Option<Acpi>> = Mutex::new(None);
+
+unsafe fn checksum(addr: u64, len: u64) -> bool {
+    (0..len).fold(0u8, |sum, i| sum.wrapping_add(read_phys::<u8>(addr + i))) == 0
+}
+
+// RSDP лежит в первом килобайте EBDA или в области BIOS 0xE0000–0xFFFFF
+// на границе 16 байт
+unsafe fn find_rsdp() -> Option<u64> {
+    let ebda = (read_phys::<u16>(0x40E) as u64) << 4;
+    let ebda_range = (ebda..ebda + 1024).step_by(16);
+    let bios_range = (0xE0000..0x100000).step_by(16);
+    ebda_range
+        .chain(bios_range)
+        .filter(|&addr| addr != 0)
+        .find(|&addr| read_phys::<[u8; 8]>(addr) == *RSDP_SIGNATURE && checksum(addr, 20))
+}
+
+unsafe fn read_table(address: u64) -> Table {
+    let length: u32 = read_phys(address + 4);
+    Table {
+        signature: read_phys(address),
+        address,
+        length,
+        revision: read_phys(address + 8),
+        oem_id: read_phys(address + 10),
+        oem_table_id: read_phys(address + 16),
+        checksum_ok: checksum(address, length as u64),
+    }
+}
+
+unsafe fn parse_fadt(table: &Table) -> Fadt {
+    let addr = table.address;
+    let len = table.length as u64;
+    let mut fadt = Fadt {
+        dsdt: read_phys::<u32>(addr + 40) as u64,
+        smi_cmd: read_phys(addr + 48),
+        acpi_enable: read_phys(addr + 52),
+        pm1a_cnt: read_phys::<u32>(addr + 64) as u16,
+        pm1b_cnt: read_phys::<u32>(addr + 68) as u16,
+        reset: None,
+    };
+    // Поля ACPI 2.0+ есть только в длинной версии таблицы
+    if len >= 129 && read_phys::<u32>(addr + 112) & RESET_REG_SUP != 0 {
+        fadt.reset = Some((GenericAddress::read(addr + 116), read_phys(addr + 128)));
+    }
+    if len >= 148 {
+        let x_dsdt: u64 = read_phys(addr + 140);
+        if x_dsdt != 0 {
+            fadt.dsdt = x_dsdt;
+        }
+    }
+    if len >= 184 && fadt.pm1a_cnt == 0 {
+        let x_pm1a = GenericAddress::read(addr + 172);
+        if x_pm1a.space == 1 {
+            fadt.pm1a_cnt = x_pm1a.address as u16;
+        }
+    }
+    fadt
+}
+
+unsafe fn parse() -> Option<Acpi> {
+    let rsdp = find_rsdp()?;
+    let revision: u8 = read_phys(rsdp + 15);
+    let rsdt: u32 = read_phys(rsdp + 16);
+    let xsdt: u64 = if revision >= 2 && checksum(rsdp, read_phys::<u32>(rsdp + 20) as u64) {
+        read_phys(rsdp + 24)
+    } else {
+        0
+    };
+
+    // XSDT хранит 64-битные адреса таблиц, RSDT — 32-битные
+    let (root, entry_size) = if xsdt != 0 {
+        (xsdt, 8)
+    } else {
+        (rsdt as u64, 4)
+    };
+    let root_table = read_table(root);
+    let count = (root_table.length as u64).saturating_sub(SDT_HEADER_SIZE) / entry_size;
+
+    let mut tables = Vec::new();
+    tables.push(root_table);
+    for i in 0..count {
+        let entry = root + SDT_HEADER_SIZE + i * entry_size;
+        let address = if entry_size == 8 {
+            read_phys::<u64>(entry)
+        } else {
+            read_phys::<u32>(entry) as u64
+        };
+        if address != 0 {
+            tables.push(read_table(address));
+        }
+    }
+
+    let fadt = tables
+        .iter()
+        .find(|table| &table.signature == b"FACP")
+        .map(|table| parse_fadt(table));
+    let s5 = fadt.and_then(|fadt| {
+        if fadt.dsdt == 0 {
+            return None;
+        }
+        let dsdt = read_table(fadt.dsdt);
+        tables.push(dsdt);
+        let aml_start = dsdt.address + SDT_HEADER_SIZE;
+        let aml_len = (dsdt.length as u64).saturating_sub(SDT_HEADER_SIZE);
+        let aml: Vec<u8> = (0..aml_len).map(|i| read_phys(aml_start + i)).collect();
+        parse_s5(&aml)
+    });
+
+    Some(Acpi {
+        revision,
+        oem_id: read_phys(rsdp + 9),
+        tables,
+        fadt,
+        s5,
+    })
+}
+
+pub fn init() {
+    let acpi = unsafe { parse() };
+    match &acpi {
+        None => log::warn!("ACPI: RSDP not found"),
+        Some(acpi) => {
+            log::info!(
+                "ACPI {}: {} tables, OEM {}",
+                if acpi.revision >= 2 { "2.0+" } else { "1.0" },
+                acpi.tables.len(),
+                text(&acpi.oem_id)
+            );
+            if acpi.fadt.is_none() {
+                log::warn!("ACPI: FADT not found");
+            }
+            if acpi.s5.is_none() {
+                log::warn!("ACPI: \\_S5 not found in DSDT");
+            }
+        }
+    }
+    *ACPI.lock() = acpi;
+}
+
+// Включает режим ACPI, если прошивка оставила систему в режиме SMM
+fn enable(fadt: &Fadt) {
+    let mut pm1a_cnt: Port<u16> = Port::new(fadt.pm1a_cnt);
+    if unsafe { pm1a_cnt.read() } & SCI_EN != 0 || fadt.smi_cmd == 0 || fadt.acpi_enable == 0 {
+        return;
+    }
+    unsafe { Port::<u8>::new(fadt.smi_cmd as u16).write(fadt.acpi_enable) };
+    if !wait_while(
+        SHUTDOWN_TIMEOUT_MS,
+        || unsafe { pm1a_cnt.read() } & SCI_EN == 0,
+    ) {
+        log::warn!("ACPI: firmware did not enable ACPI mode");
+    }
+}
+
+fn sleep_s5(fadt: &Fadt, (slp_typa, slp_typb): (u16, u16)) {
+    enable(fadt);
+    for (port, slp_typ) in [(fadt.pm1a_cnt, slp_typa), (fadt.pm1b_cnt, slp_typb)] {
+        if port == 0 {
+            continue;
+        }
+        let mut pm1_cnt: Port<u16> = Port::new(port);
+        unsafe {
+            let value = pm1_cnt.read() & !SLP_TYP_MASK;
+            pm1_cnt.write(value | (slp_typ << SLP_TYP_SHIFT) & SLP_TYP_MASK | SLP_EN);
+        }
+    }
+}
+
+// Выключает машину через ACPI (S5), затем через порты эмуляторов.
+// Возвращается, только если ни один способ не сработал.
+pub fn shutdown() {
+    // Данные копируются: во время ожидания ACPI может понадобиться другим
+    let sleep = ACPI
+        .lock()
+        .as_ref()
+        .and_then(|acpi| Some((acpi.fadt?, acpi.s5?)));
+    if let Some((fadt, s5)) = sleep {
+        sleep_s5(&fadt, s5);
+        sleep_ms(SHUTDOWN_TIMEOUT_MS);
+        log::warn!("ACPI: S5 sleep did not power off");
+    }
+
+    for (port, value) in EMULATOR_SHUTDOWN_PORTS {
+        unsafe { Port::new(port).write(value) };
+        sleep_ms(SHUTDOWN_TIMEOUT_MS / 10);
+    }
+}
+
+// Регистр сброса из FADT, если прошивка его поддерживает
+pub fn reset_register() -> Option<(GenericAddress, u8)> {
+    ACPI.lock().as_ref()?.fadt?.reset
+}
+
+// Записи MADT (таблица "APIC")
+#[derive(Debug, Clone, Copy)]
+pub enum MadtEntry {
+    LocalApic {
+        processor_id: u8,
+        apic_id: u8,
+        enabled: bool,
+    },
+    IoApic {
+        id: u8,
+        address: u32,
+        gsi_base: u32,
+    },
+    // IRQ шины ISA подключено не к одноимённому входу IOAPIC
+    InterruptOverride {
+        source: u8,
+        gsi: u32,
+        flags: u16,
+    },
+    NmiSource {
+        gsi: u32,
+        flags: u16,
+    },
+    LocalApicNmi {
+        processor_id: u8,
+        flags: u16,
+        lint: u8,
+    },
+    LocalApicAddress(u64),
+    LocalX2Apic {
+        uid: u32,
+        x2apic_id: u32,
+        enabled: bool,
+    },
+    Unknown(u8),
+}
+
+pub struct Madt {
+    pub local_apic: u64,
+    pub legacy_pics: bool, // Флаг PCAT_COMPAT: в системе есть пара 8259
+    pub entries: Vec<MadtEntry>,
+}
+
+impl Madt {
+    // Адрес LAPIC с учётом 64-битной записи-замены
+    pub fn local_apic_address(&self) -> u64 {
+        self.entries
+            .iter()
+            .find_map(|entry| match entry {
+                MadtEntry::LocalApicAddress(address) => Some(*address),
+                _ => None,
+            })
+            .unwrap_or(self.local_apic)
+    }
+}
+
+unsafe fn parse_madt(table: &Table) -> Madt {
+    let end = table.address + table.length as u64;
+    let mut entries = Vec::new();
+    let mut addr = table.address + 44;
+    while addr + 2 <= end {
+        let kind: u8 = read_phys(addr);
+        let len: u8 = read_phys(addr + 1);
+        if len < 2 || addr + len as u64 > end {
+            break;
+        }
+        entries.push(match kind {
+            0 => MadtEntry::LocalApic {
+                processor_id: read_phys(addr + 2),
+                apic_id: read_phys(addr + 3),
+                enabled: read_phys::<u32>(addr + 4) & 1 != 0,
+            },
+            1 => MadtEntry::IoApic {
+                id: read_phys(addr + 2),
+                address: read_phys(addr + 4),
+                gsi_base: read_phys(addr + 8),
+            },
+            2 => MadtEntry::InterruptOverride {
+                source: read_phys(addr + 3),
+                gsi: read_phys(addr + 4),
+                flags: read_phys(addr + 8),
+            },
+            3 => MadtEntry::NmiSource {
+                flags: read_phys(addr + 2),
+                gsi: read_phys(addr + 4),
+            },
+            4 => MadtEntry::LocalApicNmi {
+                processor_id: read_phys(addr + 2),
+                flags: read_phys(addr + 3),
+                lint: read_phys(addr + 5),
+            },
+            5 => MadtEntry::LocalApicAddress(read_phys(addr + 4)),
+            9 => MadtEntry::LocalX2Apic {
+                x2apic_id: read_phys(addr + 4),
+                enabled: read_phys::<u32>(addr + 8) & 1 != 0,
+                uid: read_phys(addr + 12),
+            },
+            kind => MadtEntry::Unknown(kind),
+        });
+        addr += len as u64;
+    }
+    Madt {
+        local_apic: read_phys::<u32>(table.address + 36) as u64,
+        legacy_pics: read_phys::<u32>(table.address + 40) & 1 != 0,
+        entries,
+    }
+}
+
+pub fn madt() -> Option<Madt> {
+    let acpi = ACPI.lock();
+    let table = acpi.as_ref()?.find(b"APIC")?;
+    Some(unsafe { parse_madt(table) })
+}
+
+pub struct Hpet {
+    pub address: GenericAddress,
+    pub number: u8,
+    pub min_tick: u16,
+    pub comparators: u8,
+    pub counter_64bit: bool,
+    pub vendor_id: u16,
+}
+
+pub fn hpet() -> Option<Hpet> {
+    let acpi = ACPI.lock();
+    let addr = acpi.as_ref()?.find(b"HPET")?.address;
+    unsafe {
+        // Event Timer Block ID: производитель, число компараторов, разрядность счётчика
+        let block_id: u32 = read_phys(addr + 36);
+        Some(Hpet {
+            address: GenericAddress::read(addr + 40),
+            number: read_phys(addr + 52),
+            min_tick: read_phys(addr + 53),
+            comparators: ((block_id >> 8) & 0x1F) as u8 + 1,
+            counter_64bit: block_id & (1 << 13) != 0,
+            vendor_id: (block_id >> 16) as u16,
+        })
+    }
+}
+
+// Область конфигурационного пространства PCI Express (ECAM) из MCFG
+#[derive(Debug, Clone, Copy)]
+pub struct McfgEntry {
+    pub base: u64,
+    pub segment: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+pub fn mcfg() -> Option<Vec<McfgEntry>> {
+    let acpi = ACPI.lock();
+    let table = acpi.as_ref()?.find(b"MCFG")?;
+    let count = (table.length as u64).saturating_sub(44) / 16;
+    let entries = (0..count)
+        .map(|i| {
+            let addr = table.address + 44 + i * 16;
+            unsafe {
+                McfgEntry {
+                    base: read_phys(addr),
+                    segment: read_phys(addr + 8),
+                    start_bus: read_phys(addr + 10),
+                    end_bus: read_phys(addr + 11),
+                }
+            }
+        })
+        .collect();
+    Some(entries)
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "acpi",
+        aliases: &[],
+        description: "List ACPI tables or dump the MADT, HPET and MCFG",
+        usage: "acpi [tables|madt|hpet|mcfg]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: 1,
+        },
+        handler: acpi_action,
+    });
+}
+
+fn acpi_action(io: &mut Io, args: &Args) -> i32 {
+    let what = args.positional.first().copied().unwrap_or("tables");
+    let found = match what {
+        "tables" => print_tables(io),
+        "madt" => madt().map(|madt| print_madt(io, &madt)),
+        "hpet" => hpet().map(|hpet| print_hpet(io, &hpet)),
+        "mcfg" => mcfg().map(|entries| print_mcfg(io, &entries)),
+        _ => {
+            eprintln!("acpi: unknown table: {}", what);
+            return 2;
+        }
+    };
+    if found.is_none() {
+        eprintln!("acpi: {} not found", what);
+        return 1;
+    }
+    0
+}
+
+// Подписи и OEM ID дополнены пробелами
+fn text(bytes: &[u8]) -> &str {
+    core::str::from_utf8(bytes).unwrap_or("?").trim_end()
+}
+
+fn print_tables(io: &mut Io) -> Option<()> {
+    let acpi = ACPI.lock();
+    let acpi = acpi.as_ref()?;
+    let _ = writeln!(
+        io,
+        "ACPI {}, OEM {}",
+        if acpi.revision >= 2 { "2.0+" } else { "1.0" },
+        text(&acpi.oem_id)
+    );
+    let _ = writeln!(io, "SIG  OEM    TABLE ID  REV  LENGTH  CHECKSUM  ADDRESS");
+    for table in &acpi.tables {
+        let _ = writeln!(
+            io,
+            "{:4} {:6} {:8}  {:3}  {:6}  {:8}  {:#x}",
+            text(&table.signature),
+            text(&table.oem_id),
+            text(&table.oem_table_id),
+            table.revision,
+            table.length,
+            if table.checksum_ok { "ok" } else { "BAD" },
+            table.address
+        );
+    }
+    Some(())
+}
+
+fn print_madt(io: &mut Io, madt: &Madt) {
+    let _ = writeln!(
+        io,
+        "Local APIC at {:#x}{}",
+        madt.local_apic_address(),
+        if madt.legacy_pics {
+            ", 8259 PICs present"
+        } else {
+            ""
+        }
+    );
+    for entry in &madt.entries {
+        let _ = match *entry {
+            MadtEntry::LocalApic {
+                processor_id,
+                apic_id,
+                enabled,
+            } => writeln!(
+                io,
+                "CPU {:3}  APIC ID {:3}  {}",
+                processor_id,
+                apic_id,
+                if enabled { "enabled" } else { "disabled" }
+            ),
+            MadtEntry::LocalX2Apic {
+                uid,
+                x2apic_id,
+                enabled,
+            } => writeln!(
+                io,
+                "CPU {:3}  x2APIC ID {}  {}",
+                uid,
+                x2apic_id,
+                if enabled { "enabled" } else { "disabled" }
+            ),
+            MadtEntry::IoApic {
+                id,
+                address,
+                gsi_base,
+            } => writeln!(
+                io,
+                "IOAPIC {}  at {:#x}  GSI base {}",
+                id, address, gsi_base
+            ),
+            MadtEntry::InterruptOverride { source, gsi, flags } => writeln!(
+                io,
+                "Override IRQ {} -> GSI {}  {}",
+                source,
+                gsi,
+                polarity_trigger(flags)
+            ),
+            MadtEntry::NmiSource { gsi, flags } => {
+                writeln!(io, "NMI source GSI {}  {}", gsi, polarity_trigger(flags))
+            }
+            MadtEntry::LocalApicNmi {
+                processor_id,
+                flags,
+                lint,
+            } => writeln!(
+                io,
+                "Local APIC NMI  CPU {}  LINT{}  {}",
+                if processor_id == 0xFF {
+                    String::from("all")
+                } else {
+                    processor_id.to_string()
+                },
+                lint,
+                polarity_trigger(flags)
+            ),
+            MadtEntry::LocalApicAddress(address) => {
+                writeln!(io, "Local APIC address override {:#x}", address)
+            }
+            MadtEntry::Unknown(kind) => writeln!(io, "Entry type {}", kind),
+        };
+    }
+}
+
+// Флаги MPS INTI: полярность (биты 0–1) и режим срабатывания (биты 2–3)
+pub fn polarity_trigger(flags: u16) -> &'static str {
+    let active_low = flags & 0x3 == 0x3;
+    let level = (flags >> 2) & 0x3 == 0x3;
+    match (active_low, level) {
+        (false, false) => "high, edge",
+        (false, true) => "high, level",
+        (true, false) => "low, edge",
+        (true, true) => "low, level",
+    }
+}
+
+fn print_hpet(io: &mut Io, hpet: &Hpet) {
+    let _ = writeln!(
+        io,
+        "HPET {} at {:#x} ({})",
+        hpet.number,
+        hpet.address.address,
+        if hpet.address.space == 0 {
+            "memory"
+        } else {
+            "I/O"
+        }
+    );
+    let _ = writeln!(io, "vendor {:#06x}", hpet.vendor_id);
+    let _ = writeln!(
+        io,
+        "{} comparators, {}-bit counter",
+        hpet.comparators,
+        if hpet.counter_64bit { 64 } else { 32 }
+    );
+    let _ = writeln!(io, "minimum tick {}", hpet.min_tick);
+}
+
+fn print_mcfg(io: &mut Io, entries: &[McfgEntry]) {
+    for entry in entries {
+        let _ = writeln!(
+            io,
+            "segment {}  buses {:02x}-{:02x}  ECAM at {:#x}",
+            entry.segment, entry.start_bus, entry.end_bus, entry.base
+        );
+    }
+}
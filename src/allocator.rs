@@ -0,0 +1,303 @@
+use crate::constants::{FRAME_SIZE, HEAP_GROW_STEP, HEAP_INITIAL_SIZE, HEAP_MAX_SIZE, HEAP_START};
+#[cfg(feature = "debug-alloc")]
+use crate::heap_debug::{self, Tracker};
+use crate::memory;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+use linked_list_allocator::Heap;
+use neonforge_core::slab::{class_index, ClassStats, BLOCK_SIZES, CLASSES};
+use spin::{Mutex, MutexGuard};
+
+// Свободный блок в списке класса размеров; хранится в самом блоке
+#[cfg(feature = "slab")]
+struct FreeBlock {
+    next: Option<&'static mut FreeBlock>,
+}
+
+// Куча в отдельной области виртуальной памяти (HEAP_START). Когда свободного
+// блока не хватает, к её концу отображаются новые кадры. С функцией `slab`
+// мелкие размещения берутся из списков блоков фиксированного размера, а
+// связный список `Heap` остаётся для крупных и для пополнения списков.
+pub struct HeapState {
+    heap: Heap,
+    #[cfg(feature = "slab")]
+    free_lists: [Option<&'static mut FreeBlock>; CLASSES],
+    classes: [ClassStats; CLASSES],
+    large: ClassStats, // Размещения больше наибольшего блока
+    used: usize,       // Байт в занятых размещениях (для блоков — размер блока)
+    peak: usize,
+    #[cfg(feature = "debug-alloc")]
+    pub tracker: Tracker,
+}
+
+#[derive(Clone, Copy)]
+pub struct HeapStats {
+    pub size: usize,
+    pub used: usize,
+    pub peak: usize,
+    pub classes: [ClassStats; CLASSES],
+    pub large: ClassStats,
+}
+
+impl HeapState {
+    const fn new() -> Self {
+        HeapState {
+            heap: Heap::empty(),
+            #[cfg(feature = "slab")]
+            free_lists: [const { None }; CLASSES],
+            classes: [ClassStats::new(); CLASSES],
+            large: ClassStats::new(),
+            used: 0,
+            peak: 0,
+            #[cfg(feature = "debug-alloc")]
+            tracker: Tracker::new(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.heap.size()
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    // Включает блоки в списках классов и фрагментацию
+    pub fn free(&self) -> usize {
+        self.size() - self.used
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            size: self.size(),
+            used: self.used,
+            peak: self.peak,
+            classes: self.classes,
+            large: self.large,
+        }
+    }
+
+    // Расширяет кучу хотя бы на `needed` байт. Блокировку держит вызывающий,
+    // поэтому отображение страниц не должно выделять память из кучи.
+    fn grow(&mut self, needed: usize) -> bool {
+        let by = needed
+            .max(HEAP_GROW_STEP)
+            .next_multiple_of(FRAME_SIZE as usize);
+        if self.heap.size() + by > HEAP_MAX_SIZE {
+            return false;
+        }
+        if memory::map_pages(self.heap.top() as u64, by).is_err() {
+            return false;
+        }
+        unsafe { self.heap.extend(by) };
+        true
+    }
+
+    fn allocate_heap(&mut self, layout: Layout) -> *mut u8 {
+        loop {
+            if let Ok(ptr) = self.heap.allocate_first_fit(layout) {
+                return ptr.as_ptr();
+            }
+            // С запасом на выравнивание и служебные данные свободного блока
+            if !self.grow(layout.size() + layout.align()) {
+                return ptr::null_mut();
+            }
+        }
+    }
+
+    #[cfg(feature = "slab")]
+    fn allocate(&mut self, class: Option<usize>, layout: Layout) -> *mut u8 {
+        let Some(class) = class else {
+            return self.allocate_heap(layout);
+        };
+        match self.free_lists[class].take() {
+            Some(block) => {
+                self.free_lists[class] = block.next.take();
+                block as *mut FreeBlock as *mut u8
+            }
+            None => {
+                let size = BLOCK_SIZES[class];
+                self.allocate_heap(Layout::from_size_align(size, size).unwrap())
+            }
+        }
+    }
+
+    #[cfg(not(feature = "slab"))]
+    fn allocate(&mut self, _class: Option<usize>, layout: Layout) -> *mut u8 {
+        self.allocate_heap(layout)
+    }
+
+    // Блоки не возвращаются в кучу, а остаются в списке своего класса
+    #[cfg(feature = "slab")]
+    unsafe fn deallocate(&mut self, ptr: *mut u8, class: Option<usize>, layout: Layout) {
+        match class {
+            Some(class) => {
+                let block = ptr as *mut FreeBlock;
+                block.write(FreeBlock {
+                    next: self.free_lists[class].take(),
+                });
+                self.free_lists[class] = Some(&mut *block);
+            }
+            None => self.heap.deallocate(NonNull::new_unchecked(ptr), layout),
+        }
+    }
+
+    #[cfg(not(feature = "slab"))]
+    unsafe fn deallocate(&mut self, ptr: *mut u8, _class: Option<usize>, layout: Layout) {
+        self.heap.deallocate(NonNull::new_unchecked(ptr), layout);
+    }
+
+    // Сколько байт занимает размещение с учётом округления до блока
+    fn charged(class: Option<usize>, layout: Layout) -> usize {
+        match class {
+            Some(class) if cfg!(feature = "slab") => BLOCK_SIZES[class],
+            _ => layout.size(),
+        }
+    }
+
+    fn counters(&mut self, class: Option<usize>) -> &mut ClassStats {
+        match class {
+            Some(class) => &mut self.classes[class],
+            None => &mut self.large,
+        }
+    }
+}
+
+pub struct KernelHeap {
+    state: Mutex<HeapState>,
+}
+
+impl KernelHeap {
+    const fn new() -> Self {
+        KernelHeap {
+            state: Mutex::new(HeapState::new()),
+        }
+    }
+
+    // Для отчётов об ошибках: куча может быть заблокирована упавшим кодом
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, HeapState>> {
+        self.state.try_lock()
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, HeapState> {
+        self.state.lock()
+    }
+}
+
+// В отладочном режиме размещение окружено заголовком и красными зонами,
+// а статистика учитывает полный размер блока
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        #[cfg(feature = "debug-alloc")]
+        let (user_layout, layout) = (layout, heap_debug::outer_layout(layout));
+        let mut state = self.state.lock();
+        let class = class_index(layout.size(), layout.align());
+        let ptr = state.allocate(class, layout);
+        if ptr.is_null() {
+            return ptr;
+        }
+        state.counters(class).record_alloc();
+        state.used += HeapState::charged(class, layout);
+        state.peak = state.peak.max(state.used);
+        #[cfg(feature = "debug-alloc")]
+        let ptr = state.tracker.on_alloc(ptr, user_layout);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut state = self.state.lock();
+        #[cfg(feature = "debug-alloc")]
+        let (ptr, layout) = (
+            state.tracker.on_free(ptr, layout),
+            heap_debug::outer_layout(layout),
+        );
+        let class = class_index(layout.size(), layout.align());
+        state.deallocate(ptr, class, layout);
+        state.counters(class).record_free();
+        state.used -= HeapState::charged(class, layout);
+    }
+}
+
+#[global_allocator]
+pub static ALLOCATOR: KernelHeap = KernelHeap::new();
+
+pub fn stats() -> HeapStats {
+    ALLOCATOR.lock().stats()
+}
+
+pub fn init() -> Result<(), &'static str> {
+    memory::map_pages(HEAP_START, HEAP_INITIAL_SIZE)?;
+    unsafe {
+        ALLOCATOR
+            .lock()
+            .heap
+            .init(HEAP_START as *mut u8, HEAP_INITIAL_SIZE)
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ALLOCATOR;
+    use crate::constants::HEAP_INITIAL_SIZE;
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test_case]
+    fn simple_allocation() {
+        let a = Box::new(41);
+        let b = Box::new(13);
+        assert_eq!(*a + *b, 54);
+    }
+
+    #[test_case]
+    fn large_vec() {
+        let n = 1000;
+        let vec: Vec<u64> = (0..n).collect();
+        assert_eq!(vec.iter().sum::<u64>(), (n - 1) * n / 2);
+    }
+
+    // Освобождённая память должна переиспользоваться
+    #[test_case]
+    fn many_boxes() {
+        for i in 0..HEAP_INITIAL_SIZE {
+            let x = Box::new(i);
+            assert_eq!(*x, i);
+        }
+    }
+
+    // Выделение больше начального размера кучи отображает новые страницы
+    #[test_case]
+    fn heap_grows() {
+        let size = 2 * HEAP_INITIAL_SIZE;
+        let big = vec![0xA5u8; size];
+        assert!(ALLOCATOR.lock().size() > size);
+        assert!(big.iter().all(|&byte| byte == 0xA5));
+    }
+
+    // Освобождённый блок сразу возвращается следующему размещению того же класса
+    #[cfg(feature = "slab")]
+    #[test_case]
+    fn slab_blocks_are_reused() {
+        let first = Box::new([0u8; 40]);
+        let address = &*first as *const _ as usize;
+        drop(first);
+        let second = Box::new([1u8; 48]);
+        assert_eq!(&*second as *const _ as usize, address);
+        assert_eq!(address % 64, 0);
+    }
+
+    #[test_case]
+    fn class_counters() {
+        let before = super::stats();
+        let boxes: Vec<Box<u64>> = (0..10).map(Box::new).collect();
+        let during = super::stats();
+        drop(boxes);
+        let after = super::stats();
+        assert_eq!(during.classes[0].allocs, before.classes[0].allocs + 10);
+        assert!(during.classes[0].peak >= 10);
+        assert_eq!(after.classes[0].live(), before.classes[0].live());
+        assert_eq!(after.used, before.used);
+    }
+}
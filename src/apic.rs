@@ -0,0 +1,534 @@
+use crate::acpi::{self, MadtEntry};
+use crate::commands::{register, Command, Io};
+use crate::constants::TICKS_PER_SECOND;
+use crate::cpu;
+use crate::memory::map_mmio;
+use crate::{eprintln, log};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use neonforge_core::args::{ArgSpec, Args};
+use neonforge_core::cpuid::Feature;
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+use x86_64::registers::model_specific::Msr;
+
+// Local APIC и IOAPIC. Настраиваются по таблице MADT; если её нет или
+// процессор без APIC, прерывания остаются на 8259 (см. interrupts).
+
+const IA32_APIC_BASE: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+const APIC_BASE_X2APIC: u64 = 1 << 10;
+
+// Регистры Local APIC (смещения в окне xAPIC, MSR x2APIC = 0x800 + смещение / 16)
+const LAPIC_ID: u32 = 0x20;
+const LAPIC_VERSION: u32 = 0x30;
+const LAPIC_TPR: u32 = 0x80;
+const LAPIC_EOI: u32 = 0xB0;
+const LAPIC_SVR: u32 = 0xF0;
+const LAPIC_ESR: u32 = 0x280;
+const LAPIC_LVT_TIMER: u32 = 0x320;
+const LAPIC_LVT_LINT0: u32 = 0x350;
+const LAPIC_LVT_LINT1: u32 = 0x360;
+const LAPIC_LVT_ERROR: u32 = 0x370;
+const LAPIC_TIMER_INITIAL: u32 = 0x380;
+const LAPIC_TIMER_CURRENT: u32 = 0x390;
+const LAPIC_TIMER_DIVIDE: u32 = 0x3E0;
+
+const SVR_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_PERIODIC: u32 = 1 << 17;
+const LVT_NMI: u32 = 0b100 << 8;
+const TIMER_DIVIDE_16: u32 = 0x3;
+const TIMER_DIVIDER: u64 = 16;
+
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+// Регистры IOAPIC
+const IOAPIC_VERSION: u32 = 0x01;
+const IOAPIC_REDIRECTION: u32 = 0x10;
+
+const REDIRECT_DELIVERY: u64 = 0b111 << 8;
+const REDIRECT_NMI: u64 = 0b100 << 8;
+const REDIRECT_ACTIVE_LOW: u64 = 1 << 13;
+const REDIRECT_LEVEL: u64 = 1 << 15;
+const REDIRECT_MASKED: u64 = 1 << 16;
+
+// Калибровка таймера LAPIC по каналу 2 PIT
+const PIT_FREQUENCY: u64 = 1_193_182;
+const CALIBRATION_MS: u64 = 10;
+const CALIBRATION_MAX_POLLS: u32 = 1_000_000; // Чтение порта занимает около микросекунды
+
+#[derive(Clone, Copy)]
+struct LocalApic {
+    base: usize, // Окно xAPIC; в режиме x2APIC не используется
+    x2apic: bool,
+}
+
+impl LocalApic {
+    unsafe fn read(&self, reg: u32) -> u32 {
+        if self.x2apic {
+            Msr::new(0x800 + (reg >> 4)).read() as u32
+        } else {
+            ptr::read_volatile((self.base + reg as usize) as *const u32)
+        }
+    }
+
+    unsafe fn write(&self, reg: u32, value: u32) {
+        if self.x2apic {
+            Msr::new(0x800 + (reg >> 4)).write(value as u64);
+        } else {
+            ptr::write_volatile((self.base + reg as usize) as *mut u32, value);
+        }
+    }
+
+    unsafe fn id(&self) -> u32 {
+        let id = self.read(LAPIC_ID);
+        if self.x2apic {
+            id
+        } else {
+            id >> 24
+        }
+    }
+}
+
+struct IoApic {
+    id: u8,
+    base: usize,
+    gsi_base: u32,
+    inputs: u32,
+}
+
+impl IoApic {
+    unsafe fn read(&self, reg: u32) -> u32 {
+        ptr::write_volatile(self.base as *mut u32, reg);
+        ptr::read_volatile((self.base + 0x10) as *const u32)
+    }
+
+    unsafe fn write(&self, reg: u32, value: u32) {
+        ptr::write_volatile(self.base as *mut u32, reg);
+        ptr::write_volatile((self.base + 0x10) as *mut u32, value);
+    }
+
+    unsafe fn read_entry(&self, input: u32) -> u64 {
+        let reg = IOAPIC_REDIRECTION + input * 2;
+        self.read(reg) as u64 | (self.read(reg + 1) as u64) << 32
+    }
+
+    // Старшая половина пишется первой, пока вход ещё замаскирован
+    unsafe fn write_entry(&self, input: u32, entry: u64) {
+        let reg = IOAPIC_REDIRECTION + input * 2;
+        self.write(reg, REDIRECT_MASKED as u32);
+        self.write(reg + 1, (entry >> 32) as u32);
+        self.write(reg, entry as u32);
+    }
+
+    fn handles(&self, gsi: u32) -> bool {
+        (self.gsi_base..self.gsi_base + self.inputs).contains(&gsi)
+    }
+}
+
+// Перенаправление IRQ шины ISA: номер GSI и флаги MPS INTI
+#[derive(Clone, Copy)]
+struct IsaRoute {
+    gsi: u32,
+    flags: u16,
+}
+
+struct Apic {
+    ioapics: Vec<IoApic>,
+    isa: [IsaRoute; 16],
+}
+
+// Регистры Local APIC меняются только при загрузке, дальше их читают
+// обработчики прерываний без блокировок
+static mut LAPIC: LocalApic = LocalApic {
+    base: 0,
+    x2apic: false,
+};
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMER_TICKS_PER_MS: AtomicU32 = AtomicU32::new(0); // 0 — таймер LAPIC не запущен
+static APIC: Mutex<Option<Apic>> = Mutex::new(None);
+
+fn lapic() -> LocalApic {
+    unsafe { LAPIC }
+}
+
+// Прерывания идут через Local APIC и IOAPIC
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Тики отсчитывает таймер LAPIC, а не PIT
+pub fn timer_enabled() -> bool {
+    TIMER_TICKS_PER_MS.load(Ordering::Relaxed) != 0
+}
+
+pub fn end_of_interrupt() {
+    unsafe { lapic().write(LAPIC_EOI, 0) }
+}
+
+// Полярность и режим срабатывания из флагов MPS INTI. По умолчанию («как
+// у шины») ISA — активный высокий уровень по фронту.
+fn redirection_flags(flags: u16) -> u64 {
+    let mut entry = 0;
+    if flags & 0x3 == 0x3 {
+        entry |= REDIRECT_ACTIVE_LOW;
+    }
+    if (flags >> 2) & 0x3 == 0x3 {
+        entry |= REDIRECT_LEVEL;
+    }
+    entry
+}
+
+unsafe fn init_lapic(madt: &acpi::Madt) -> Result<LocalApic, &'static str> {
+    let mut base = Msr::new(IA32_APIC_BASE);
+    let value = base.read() | APIC_BASE_ENABLE;
+    base.write(value);
+    // Переход в x2APIC допустим только из включённого xAPIC (SDM 10.12.5),
+    // поэтому EXTD выставляется отдельной записью
+    let x2apic = cpu::has(Feature::X2Apic);
+    if x2apic {
+        base.write(value | APIC_BASE_X2APIC);
+    }
+
+    let lapic = LocalApic {
+        base: if x2apic {
+            0
+        } else {
+            map_mmio(madt.local_apic_address(), 0x1000)? as usize
+        },
+        x2apic,
+    };
+    // Все локальные источники замаскированы, NMI настраиваются по MADT ниже
+    for lvt in [
+        LAPIC_LVT_TIMER,
+        LAPIC_LVT_LINT0,
+        LAPIC_LVT_LINT1,
+        LAPIC_LVT_ERROR,
+    ] {
+        lapic.write(lvt, LVT_MASKED);
+    }
+    lapic.write(LAPIC_ESR, 0);
+    lapic.write(LAPIC_TPR, 0);
+    lapic.write(LAPIC_SVR, SVR_ENABLE | SPURIOUS_VECTOR as u32);
+
+    // Номер процессора ACPI для записей NMI. Процессоры с ID больше 254
+    // описываются записями x2APIC.
+    let id = lapic.id();
+    let processor = madt.entries.iter().find_map(|entry| match *entry {
+        MadtEntry::LocalApic {
+            processor_id,
+            apic_id,
+            ..
+        } if apic_id as u32 == id => Some(processor_id as u32),
+        MadtEntry::LocalX2Apic { uid, x2apic_id, .. } if x2apic_id == id => Some(uid),
+        _ => None,
+    });
+    for entry in &madt.entries {
+        if let MadtEntry::LocalApicNmi {
+            processor_id,
+            flags,
+            lint,
+        } = *entry
+        {
+            if processor_id == 0xFF || Some(processor_id as u32) == processor {
+                let lvt = if lint == 0 {
+                    LAPIC_LVT_LINT0
+                } else {
+                    LAPIC_LVT_LINT1
+                };
+                lapic.write(lvt, LVT_NMI | redirection_flags(flags) as u32);
+            }
+        }
+    }
+    lapic.write(LAPIC_EOI, 0);
+    Ok(lapic)
+}
+
+unsafe fn init_ioapics(madt: &acpi::Madt) -> Result<Apic, &'static str> {
+    let mut apic = Apic {
+        ioapics: Vec::new(),
+        isa: core::array::from_fn(|irq| IsaRoute {
+            gsi: irq as u32,
+            flags: 0,
+        }),
+    };
+    for entry in &madt.entries {
+        match *entry {
+            MadtEntry::IoApic {
+                id,
+                address,
+                gsi_base,
+            } => {
+                let mut ioapic = IoApic {
+                    id,
+                    base: map_mmio(address as u64, 0x1000)? as usize,
+                    gsi_base,
+                    inputs: 0,
+                };
+                ioapic.inputs = ((ioapic.read(IOAPIC_VERSION) >> 16) & 0xFF) + 1;
+                for input in 0..ioapic.inputs {
+                    ioapic.write_entry(input, REDIRECT_MASKED);
+                }
+                apic.ioapics.push(ioapic);
+            }
+            MadtEntry::InterruptOverride { source, gsi, flags } if source < 16 => {
+                apic.isa[source as usize] = IsaRoute { gsi, flags };
+            }
+            _ => {}
+        }
+    }
+    if apic.ioapics.is_empty() {
+        return Err("no IOAPIC in MADT");
+    }
+    for entry in &madt.entries {
+        if let MadtEntry::NmiSource { gsi, flags } = *entry {
+            apic.write_gsi(gsi, REDIRECT_NMI | redirection_flags(flags));
+        }
+    }
+    Ok(apic)
+}
+
+impl Apic {
+    fn write_gsi(&self, gsi: u32, entry: u64) -> bool {
+        match self.ioapics.iter().find(|ioapic| ioapic.handles(gsi)) {
+            Some(ioapic) => {
+                unsafe { ioapic.write_entry(gsi - ioapic.gsi_base, entry) };
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Включает Local APIC и IOAPIC по таблице MADT. Вызывается при выключенных
+// прерываниях; false — остаёмся на 8259.
+pub fn init() -> bool {
+    if !cpu::has(Feature::Apic) {
+        log::warn!("APIC: not supported by the CPU, using 8259 PIC");
+        return false;
+    }
+    let Some(madt) = acpi::madt() else {
+        log::warn!("APIC: no MADT, using 8259 PIC");
+        return false;
+    };
+    let result = unsafe {
+        init_ioapics(&madt).and_then(|apic| init_lapic(&madt).map(|lapic| (apic, lapic)))
+    };
+    let (apic, lapic) = match result {
+        Ok(result) => result,
+        Err(msg) => {
+            log::warn!("APIC: {}, using 8259 PIC", msg);
+            return false;
+        }
+    };
+    unsafe { LAPIC = lapic };
+    log::info!(
+        "APIC: {} ID {}, {} IOAPIC(s)",
+        if lapic.x2apic { "x2APIC" } else { "xAPIC" },
+        unsafe { lapic.id() },
+        apic.ioapics.len()
+    );
+    *APIC.lock() = Some(apic);
+    ENABLED.store(true, Ordering::Relaxed);
+    true
+}
+
+// Направляет IRQ шины ISA на `vector` загрузочного процессора с учётом
+// переопределений из MADT
+pub fn route_isa_irq(irq: u8, vector: u8) -> bool {
+    let apic = APIC.lock();
+    let Some(apic) = apic.as_ref() else {
+        return false;
+    };
+    let route = apic.isa[irq as usize];
+    let destination = (unsafe { lapic().id() } as u64 & 0xFF) << 56;
+    apic.write_gsi(
+        route.gsi,
+        vector as u64 | redirection_flags(route.flags) | destination,
+    )
+}
+
+// Считает такты таймера LAPIC за CALIBRATION_MS по однократному счёту
+// канала 2 PIT (его выход виден в бите 5 порта 0x61)
+unsafe fn calibrate_timer(lapic: LocalApic) -> u32 {
+    let mut control: Port<u8> = Port::new(0x61);
+    let mut command: Port<u8> = Port::new(0x43);
+    let mut data: Port<u8> = Port::new(0x42);
+    let count = (PIT_FREQUENCY * CALIBRATION_MS / 1000) as u16;
+
+    // Вентиль закрыт, динамик отключён
+    let saved = control.read();
+    control.write(saved & !0x3);
+    command.write(0xB0); // Канал 2, младший и старший байты, режим 0
+    data.write(count as u8);
+    data.write((count >> 8) as u8);
+
+    lapic.write(LAPIC_TIMER_DIVIDE, TIMER_DIVIDE_16);
+    lapic.write(LAPIC_LVT_TIMER, LVT_MASKED);
+    lapic.write(LAPIC_TIMER_INITIAL, u32::MAX);
+    control.write((saved & !0x3) | 0x1);
+    // Без канала 2 выход не поднимется: ждём не дольше, чем досчитает LAPIC,
+    // и не больше CALIBRATION_MAX_POLLS опросов
+    let mut fired = false;
+    for _ in 0..CALIBRATION_MAX_POLLS {
+        if control.read() & 0x20 != 0 {
+            fired = true;
+            break;
+        }
+        if lapic.read(LAPIC_TIMER_CURRENT) == 0 {
+            break;
+        }
+        core::hint::spin_loop();
+    }
+    let elapsed = u32::MAX - lapic.read(LAPIC_TIMER_CURRENT);
+    lapic.write(LAPIC_TIMER_INITIAL, 0);
+    control.write(saved);
+    if !fired {
+        return 0;
+    }
+    (elapsed as u64 / CALIBRATION_MS) as u32
+}
+
+// Запускает периодический таймер LAPIC с частотой TICKS_PER_SECOND на
+// `vector`. false — APIC не включён или калибровка не удалась, тики
+// остаются за PIT.
+pub fn start_timer(vector: u8) -> bool {
+    if !enabled() {
+        return false;
+    }
+    let lapic = lapic();
+    unsafe {
+        let per_ms = calibrate_timer(lapic);
+        let initial = per_ms as u64 * 1000 / TICKS_PER_SECOND as u64;
+        if initial == 0 || initial > u32::MAX as u64 {
+            log::warn!("APIC: timer calibration failed, using PIT");
+            return false;
+        }
+        lapic.write(LAPIC_LVT_TIMER, LVT_PERIODIC | vector as u32);
+        lapic.write(LAPIC_TIMER_INITIAL, initial as u32);
+        TIMER_TICKS_PER_MS.store(per_ms, Ordering::Relaxed);
+    }
+    log::info!(
+        "APIC: timer at {} Hz, bus {} MHz",
+        TICKS_PER_SECOND,
+        per_ms_to_mhz(TIMER_TICKS_PER_MS.load(Ordering::Relaxed))
+    );
+    true
+}
+
+fn per_ms_to_mhz(per_ms: u32) -> u64 {
+    per_ms as u64 * TIMER_DIVIDER / 1000
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "apic",
+        aliases: &[],
+        description: "Show the interrupt controller, timer source and IOAPIC routes",
+        usage: "apic",
+        args: ArgSpec::NONE,
+        handler: apic_action,
+    });
+}
+
+fn apic_action(io: &mut Io, _args: &Args) -> i32 {
+    if !enabled() {
+        let _ = writeln!(io, "8259 PIC, timer: PIT at {} Hz", TICKS_PER_SECOND);
+        return 0;
+    }
+    let lapic = lapic();
+    let (id, version) = unsafe { (lapic.id(), lapic.read(LAPIC_VERSION)) };
+    let _ = writeln!(
+        io,
+        "Local APIC ID {} ({}), version {:#x}, {} LVT entries",
+        id,
+        if lapic.x2apic { "x2APIC" } else { "xAPIC" },
+        version & 0xFF,
+        ((version >> 16) & 0xFF) + 1
+    );
+    let per_ms = TIMER_TICKS_PER_MS.load(Ordering::Relaxed);
+    if per_ms != 0 {
+        let _ = writeln!(
+            io,
+            "Timer: LAPIC at {} Hz, bus {} MHz",
+            TICKS_PER_SECOND,
+            per_ms_to_mhz(per_ms)
+        );
+    } else {
+        let _ = writeln!(io, "Timer: PIT at {} Hz", TICKS_PER_SECOND);
+    }
+
+    // Маршруты копируются: вывод может идти в кучу, а в обработчиках
+    // прерываний блокировка не берётся
+    let mut routes = Vec::new();
+    {
+        let apic = APIC.lock();
+        let Some(apic) = apic.as_ref() else {
+            eprintln!("apic: not initialized");
+            return 1;
+        };
+        for ioapic in &apic.ioapics {
+            routes.push((ioapic.id, ioapic.gsi_base, ioapic.inputs, None));
+            for input in 0..ioapic.inputs {
+                let entry = unsafe { ioapic.read_entry(input) };
+                if entry & REDIRECT_MASKED == 0 {
+                    let gsi = ioapic.gsi_base + input;
+                    let irq = apic.isa.iter().position(|route| route.gsi == gsi);
+                    routes.push((ioapic.id, gsi, 0, Some((entry, irq))));
+                }
+            }
+        }
+    }
+    for (id, gsi, inputs, route) in routes {
+        let Some((entry, irq)) = route else {
+            let _ = writeln!(io, "IOAPIC {}: GSI {}-{}", id, gsi, gsi + inputs - 1);
+            continue;
+        };
+        // Флаги записи обратно в формат MPS INTI
+        let polarity = if entry & REDIRECT_ACTIVE_LOW != 0 {
+            0x3
+        } else {
+            0x1
+        };
+        let trigger = if entry & REDIRECT_LEVEL != 0 {
+            0xC
+        } else {
+            0x4
+        };
+        let _ = write!(
+            io,
+            "  GSI {:3} -> vector {:#04x}  {}",
+            gsi,
+            entry & 0xFF,
+            acpi::polarity_trigger(polarity | trigger)
+        );
+        let _ = match irq {
+            _ if entry & REDIRECT_DELIVERY == REDIRECT_NMI => writeln!(io, " (NMI)"),
+            Some(irq) => writeln!(io, " (IRQ {})", irq),
+            None => writeln!(io),
+        };
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        enabled, lapic, timer_enabled, LAPIC_LVT_TIMER, LAPIC_SVR, LVT_MASKED, SVR_ENABLE,
+    };
+
+    #[test_case]
+    fn lapic_timer_is_running() {
+        // QEMU предоставляет MADT и APIC
+        assert!(enabled());
+        assert!(timer_enabled());
+        let lapic = lapic();
+        unsafe {
+            assert!(lapic.read(LAPIC_SVR) & SVR_ENABLE != 0);
+            assert!(lapic.read(LAPIC_LVT_TIMER) & LVT_MASKED == 0);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::log;
+use alloc::string::String;
+use alloc::vec::Vec;
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+
+pub const SECTOR_SIZE: usize = 512;
+
+// Регистры относительно базового порта канала
+const REG_DATA: u16 = 0;
+const REG_ERROR: u16 = 1;
+const REG_COUNT: u16 = 2;
+const REG_LBA_LOW: u16 = 3;
+const REG_LBA_MID: u16 = 4;
+const REG_LBA_HIGH: u16 = 5;
+const REG_DRIVE: u16 = 6;
+const REG_STATUS: u16 = 7; // При записи — регистр команды
+
+const STATUS_ERR: u8 = 1 << 0;
+const STATUS_DRQ: u8 = 1 << 3;
+const STATUS_DF: u8 = 1 << 5;
+const STATUS_BSY: u8 = 1 << 7;
+
+const CMD_READ: u8 = 0x20; // READ SECTORS, LBA28
+const CMD_WRITE: u8 = 0x30; // WRITE SECTORS, LBA28
+const CMD_FLUSH: u8 = 0xE7;
+const CMD_IDENTIFY: u8 = 0xEC;
+
+const MAX_POLLS: usize = 1_000_000;
+const MAX_SECTORS: usize = 256; // За одну команду LBA28
+
+// Два канала IDE: базовый порт и порт управления
+const CHANNELS: [(u16, u16); 2] = [(0x1F0, 0x3F6), (0x170, 0x376)];
+
+#[derive(Clone)]
+pub struct Drive {
+    pub channel: usize,
+    pub slave: bool,
+    pub sectors: u32,
+    pub model: String,
+    base: u16,
+    control: u16,
+}
+
+static DRIVES: Mutex<Vec<Drive>> = Mutex::new(Vec::new());
+// Команды устройствам одного канала не должны перемежаться
+static CHANNEL_LOCKS: [Mutex<()>; 2] = [Mutex::new(()), Mutex::new(())];
+
+impl Drive {
+    // Имя вида ata0.1: канал и номер устройства на нём
+    pub fn name(&self) -> String {
+        alloc::format!("ata{}.{}", self.channel, self.slave as u8)
+    }
+
+    // С ведущего устройства первичного канала загружается ядро
+    pub fn is_boot_disk(&self) -> bool {
+        self.channel == 0 && !self.slave
+    }
+
+    unsafe fn read_reg(&self, reg: u16) -> u8 {
+        Port::new(self.base + reg).read()
+    }
+
+    unsafe fn write_reg(&self, reg: u16, value: u8) {
+        Port::new(self.base + reg).write(value)
+    }
+
+    // Около 400 нс на смену устройства: четыре чтения альтернативного статуса
+    unsafe fn delay(&self) {
+        let mut status = Port::<u8>::new(self.control);
+        for _ in 0..4 {
+            status.read();
+        }
+    }
+
+    unsafe fn select(&self, lba_high: u8) {
+        self.write_reg(REG_DRIVE, 0xE0 | (self.slave as u8) << 4 | lba_high & 0x0F);
+        self.delay();
+    }
+
+    // Ждёт, пока устройство освободится; при `drq` — и готовности данных
+    unsafe fn wait(&self, drq: bool) -> Result<(), &'static str> {
+        for _ in 0..MAX_POLLS {
+            let status = self.read_reg(REG_STATUS);
+            if status & STATUS_BSY != 0 {
+                continue;
+            }
+            if status & (STATUS_ERR | STATUS_DF) != 0 {
+                return Err("device error");
+            }
+            if !drq || status & STATUS_DRQ != 0 {
+                return Ok(());
+            }
+        }
+        Err("timeout")
+    }
+
+    unsafe fn command(&self, cmd: u8, lba: u32, count: usize) -> Result<(), &'static str> {
+        if count == 0 || count > MAX_SECTORS || lba as u64 + count as u64 > self.sectors as u64 {
+            return Err("sector out of range");
+        }
+        self.select((lba >> 24) as u8);
+        self.wait(false)?;
+        self.write_reg(REG_COUNT, count as u8); // 0 означает 256
+        self.write_reg(REG_LBA_LOW, lba as u8);
+        self.write_reg(REG_LBA_MID, (lba >> 8) as u8);
+        self.write_reg(REG_LBA_HIGH, (lba >> 16) as u8);
+        self.write_reg(REG_STATUS, cmd);
+        Ok(())
+    }
+
+    // Читает `buf.len() / 512` секторов начиная с `lba`
+    pub fn read(&self, lba: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        let _channel = CHANNEL_LOCKS[self.channel].lock();
+        let mut data = Port::<u16>::new(self.base + REG_DATA);
+        unsafe {
+            self.command(CMD_READ, lba, buf.len() / SECTOR_SIZE)?;
+            for sector in buf.chunks_exact_mut(SECTOR_SIZE) {
+                self.wait(true)?;
+                for word in sector.chunks_exact_mut(2) {
+                    word.copy_from_slice(&data.read().to_le_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, lba: u32, buf: &[u8]) -> Result<(), &'static str> {
+        let _channel = CHANNEL_LOCKS[self.channel].lock();
+        let mut data = Port::<u16>::new(self.base + REG_DATA);
+        unsafe {
+            self.command(CMD_WRITE, lba, buf.len() / SECTOR_SIZE)?;
+            for sector in buf.chunks_exact(SECTOR_SIZE) {
+                self.wait(true)?;
+                for word in sector.chunks_exact(2) {
+                    data.write(u16::from_le_bytes([word[0], word[1]]));
+                }
+            }
+            // Данные могли остаться в кэше устройства
+            self.write_reg(REG_STATUS, CMD_FLUSH);
+            self.wait(false)
+        }
+    }
+}
+
+// IDENTIFY DEVICE. None — устройства нет или это не ATA (ATAPI, SATA).
+unsafe fn identify(channel: usize, slave: bool) -> Option<Drive> {
+    let (base, control) = CHANNELS[channel];
+    let mut drive = Drive {
+        channel,
+        slave,
+        sectors: 0,
+        model: String::new(),
+        base,
+        control,
+    };
+    drive.select(0);
+    for reg in [REG_COUNT, REG_LBA_LOW, REG_LBA_MID, REG_LBA_HIGH] {
+        drive.write_reg(reg, 0);
+    }
+    drive.write_reg(REG_STATUS, CMD_IDENTIFY);
+    // 0 — устройства нет, 0xFF — на канале никого нет вовсе
+    if matches!(drive.read_reg(REG_STATUS), 0 | 0xFF) {
+        return None;
+    }
+    for _ in 0..MAX_POLLS {
+        if drive.read_reg(REG_STATUS) & STATUS_BSY == 0 {
+            break;
+        }
+    }
+    // ATAPI отвечает на IDENTIFY ошибкой и подписью в регистрах LBA
+    if drive.read_reg(REG_LBA_MID) != 0 || drive.read_reg(REG_LBA_HIGH) != 0 {
+        return None;
+    }
+    if drive.wait(true).is_err() {
+        let _ = drive.read_reg(REG_ERROR);
+        return None;
+    }
+
+    let mut data = Port::<u16>::new(base + REG_DATA);
+    let mut id = [0u16; 256];
+    for word in id.iter_mut() {
+        *word = data.read();
+    }
+    // Слова 60–61: число секторов, доступных по LBA28
+    drive.sectors = id[60] as u32 | (id[61] as u32) << 16;
+    // Слова 27–46: модель, в каждом слове байты переставлены
+    let model: Vec<u8> = id[27..47].iter().flat_map(|w| w.to_be_bytes()).collect();
+    drive.model = String::from(String::from_utf8_lossy(&model).trim());
+    (drive.sectors != 0).then_some(drive)
+}
+
+// Опрашивает оба канала IDE. Прерывания не используются: драйвер
+// ждёт готовности устройства, читая регистр статуса.
+pub fn init() {
+    let mut drives = DRIVES.lock();
+    drives.clear();
+    for channel in 0..CHANNELS.len() {
+        for slave in [false, true] {
+            if let Some(drive) = unsafe { identify(channel, slave) } {
+                log::info!(
+                    "ATA {}: {}, {} MiB",
+                    drive.name(),
+                    drive.model,
+                    drive.sectors as usize * SECTOR_SIZE / (1024 * 1024)
+                );
+                drives.push(drive);
+            }
+        }
+    }
+}
+
+pub fn drives() -> Vec<Drive> {
+    DRIVES.lock().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{drives, SECTOR_SIZE};
+
+    #[test_case]
+    fn boot_disk_is_found() {
+        // Тесты запускаются с образа, подключённого ведущим к первичному каналу
+        let drives = drives();
+        let boot = drives.iter().find(|d| d.is_boot_disk()).unwrap();
+        let mut sector = [0; SECTOR_SIZE];
+        boot.read(0, &mut sector).unwrap();
+        // Загрузочная сигнатура MBR
+        assert_eq!(&sector[510..], &[0x55, 0xAA]);
+    }
+}
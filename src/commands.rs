@@ -1,237 +1,606 @@
-use crate::constants::{COLS, CURRENT_COL, CURRENT_ROW, ROWS};
+use crate::constants::COLS;
 use crate::datetime::{get_date, get_time, set_date, set_time};
-use crate::vga::{clear_screen, write_char};
-use core::arch::asm;
+use crate::eprintln;
+use crate::fs::FS;
+use crate::shell;
+use crate::{acpi, console, fs, power};
+use core::fmt::{self, Write};
+use neonforge_core::args::{parse, ArgSpec, Args, OptSpec};
 
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
-
-struct Command<'a> {
-    name: &'a str,
-    action: fn(*mut [[u8; COLS]; ROWS], usize) -> bool,
+use spin::Mutex;
+
+// Потоки команды. `stdin` — вывод предыдущей команды конвейера или
+// содержимое файла после `<`; `None`, если ввод не перенаправлен.
+// Вывод идёт на экран, в следующую команду или в файл после `>`/`>>`.
+pub struct Io<'a> {
+    pub stdin: Option<&'a str>,
+    pub stdout: &'a mut dyn Write,
 }
 
-impl<'a> Command<'a> {
-    fn new(name: &'a str, action: fn(*mut [[u8; COLS]; ROWS], usize) -> bool) -> Self {
-        Command { name, action }
+impl Write for Io<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.stdout.write_str(s)
     }
 }
 
-fn hello_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let msg = b"HELLO!";
-        for (i, &byte) in msg.iter().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
-        }
-        false
-    }
+// Обработчик получает потоки и разобранные аргументы и возвращает код завершения
+pub type Handler = fn(&mut Io, &Args) -> i32;
+
+#[derive(Clone, Copy)]
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub description: &'static str,
+    pub usage: &'static str,
+    pub args: ArgSpec,
+    pub handler: Handler,
 }
 
-fn time_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let time = get_time();
-        let time_str = format!("{:02}:{:02}:{:02}", time.0, time.1, time.2);
+static REGISTRY: Mutex<Vec<Command>> = Mutex::new(Vec::new());
 
-        for (i, byte) in time_str.bytes().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
-        }
-        false
+// Добавляет команду в реестр. Модули регистрируют свои команды при инициализации.
+pub fn register(command: Command) {
+    REGISTRY.lock().push(command);
+}
+
+fn find(name: &str) -> Option<Command> {
+    REGISTRY
+        .lock()
+        .iter()
+        .find(|cmd| cmd.name == name || cmd.aliases.contains(&name))
+        .copied()
+}
+
+// Имена и псевдонимы всех команд (для дополнения по Tab)
+pub fn command_names() -> Vec<&'static str> {
+    let registry = REGISTRY.lock();
+    let mut names = Vec::new();
+    for cmd in registry.iter() {
+        names.push(cmd.name);
+        names.extend_from_slice(cmd.aliases);
     }
+    names
 }
 
-fn date_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let date = get_date();
-        let date_str = format!("{:02}.{:02}.{:04}", date.0, date.1, date.2);
+pub fn init() {
+    register(Command {
+        name: "help",
+        aliases: &["?"],
+        description: "List commands or show help for one",
+        usage: "help [COMMAND]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: 1,
+        },
+        handler: help_action,
+    });
+    register(Command {
+        name: "hello",
+        aliases: &[],
+        description: "Print HELLO!",
+        usage: "hello",
+        args: ArgSpec::NONE,
+        handler: hello_action,
+    });
+    register(Command {
+        name: "time",
+        aliases: &[],
+        description: "Show the system time",
+        usage: "time",
+        args: ArgSpec::NONE,
+        handler: time_action,
+    });
+    register(Command {
+        name: "time_set",
+        aliases: &[],
+        description: "Set the system time",
+        usage: "time_set HH:MM:SS",
+        args: ArgSpec {
+            options: &[],
+            min: 1,
+            max: 1,
+        },
+        handler: time_set_action,
+    });
+    register(Command {
+        name: "date",
+        aliases: &[],
+        description: "Show the system date",
+        usage: "date",
+        args: ArgSpec::NONE,
+        handler: date_action,
+    });
+    register(Command {
+        name: "date_set",
+        aliases: &[],
+        description: "Set the system date",
+        usage: "date_set DD.MM.YYYY",
+        args: ArgSpec {
+            options: &[],
+            min: 1,
+            max: 1,
+        },
+        handler: date_set_action,
+    });
+    register(Command {
+        name: "error",
+        aliases: &[],
+        description: "Print the unknown command error",
+        usage: "error",
+        args: ArgSpec::NONE,
+        handler: error_action,
+    });
+    register(Command {
+        name: "reboot",
+        aliases: &["restart"],
+        description: "Restart the system",
+        usage: "reboot",
+        args: ArgSpec::NONE,
+        handler: reboot_action,
+    });
+    register(Command {
+        name: "shutdown",
+        aliases: &["poweroff"],
+        description: "Turn off the system",
+        usage: "shutdown",
+        args: ArgSpec::NONE,
+        handler: shutdown_action,
+    });
+    register(Command {
+        name: "clear",
+        aliases: &["cls"],
+        description: "Clear the terminal",
+        usage: "clear",
+        args: ArgSpec::NONE,
+        handler: clear,
+    });
+    register(Command {
+        name: "echo",
+        aliases: &[],
+        description: "Print arguments",
+        usage: "echo [-n] [TEXT...]",
+        args: ArgSpec {
+            options: &[OptSpec {
+                name: "no-newline",
+                short: Some('n'),
+                takes_value: false,
+            }],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: echo_action,
+    });
+    register(Command {
+        name: "grep",
+        aliases: &[],
+        description: "Print lines containing a pattern",
+        usage: "grep [-i] [-v] PATTERN [FILE...]",
+        args: ArgSpec {
+            options: &[
+                OptSpec {
+                    name: "ignore-case",
+                    short: Some('i'),
+                    takes_value: false,
+                },
+                OptSpec {
+                    name: "invert-match",
+                    short: Some('v'),
+                    takes_value: false,
+                },
+            ],
+            min: 1,
+            max: usize::MAX,
+        },
+        handler: grep_action,
+    });
+    register(Command {
+        name: "wc",
+        aliases: &[],
+        description: "Count lines, words and bytes",
+        usage: "wc [FILE...]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: wc_action,
+    });
+    register(Command {
+        name: "test",
+        aliases: &["["],
+        description: "Check a condition for if/while",
+        usage: "test EXPRESSION",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: test_action,
+    });
+    register(Command {
+        name: "expr",
+        aliases: &[],
+        description: "Evaluate an integer expression",
+        usage: "expr NUMBER OP NUMBER",
+        args: ArgSpec {
+            options: &[],
+            min: 3,
+            max: 3,
+        },
+        handler: expr_action,
+    });
+    register(Command {
+        name: "true",
+        aliases: &[],
+        description: "Return success",
+        usage: "true",
+        args: ArgSpec::NONE,
+        handler: true_action,
+    });
+    register(Command {
+        name: "false",
+        aliases: &[],
+        description: "Return failure",
+        usage: "false",
+        args: ArgSpec::NONE,
+        handler: false_action,
+    });
+}
 
-        for (i, byte) in date_str.bytes().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
+fn help_action(io: &mut Io, args: &Args) -> i32 {
+    if let [name] = args.positional[..] {
+        let Some(cmd) = find(name) else {
+            eprintln!("help: no such command: {}", name);
+            return 1;
+        };
+        let _ = writeln!(io, "{} - {}", cmd.name, cmd.description);
+        let _ = writeln!(io, "Usage: {}", cmd.usage);
+        if !cmd.aliases.is_empty() {
+            let _ = writeln!(io, "Aliases: {}", cmd.aliases.join(", "));
         }
-        false
+        for option in cmd.args.options {
+            let short = match option.short {
+                Some(short) => format!("-{}, ", short),
+                None => String::new(),
+            };
+            let value = if option.takes_value { " VALUE" } else { "" };
+            let _ = writeln!(io, "  {}--{}{}", short, option.name, value);
+        }
+        return 0;
+    }
+
+    // Копируем реестр, чтобы не держать блокировку во время вывода
+    let commands: Vec<Command> = REGISTRY.lock().clone();
+    let width = commands.iter().map(|cmd| cmd.name.len()).max().unwrap_or(0) + 2;
+    for cmd in commands {
+        let _ = writeln!(io, "{:width$}{}", cmd.name, cmd.description, width = width);
     }
+    0
 }
 
-fn date_set_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let command: &[u8] = &(*buffer)[row][12..22]; // Извлекаем аргументы после `time_add`
+fn hello_action(io: &mut Io, _args: &Args) -> i32 {
+    let _ = writeln!(io, "HELLO!");
+    0
+}
 
-        let command_str = core::str::from_utf8(command).unwrap_or("").trim();
+fn time_action(io: &mut Io, _args: &Args) -> i32 {
+    let time = get_time();
+    let _ = writeln!(io, "{:02}:{:02}:{:02}", time.0, time.1, time.2);
+    0
+}
 
-        let mut parts = command_str.split('.');
+fn date_action(io: &mut Io, _args: &Args) -> i32 {
+    let date = get_date();
+    let _ = writeln!(io, "{:02}.{:02}.{:04}", date.0, date.1, date.2);
+    0
+}
 
-        if let (Some(d), Some(m), Some(y)) = (parts.next(), parts.next(), parts.next()) {
-            if let (Ok(day), Ok(month), Ok(year)) =
-                (d.parse::<u8>(), m.parse::<u8>(), y.parse::<u16>())
-            {
-                set_date(day, month, year);
-                let msg = b"Date set!";
-                for (i, &byte) in msg.iter().enumerate() {
-                    write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-                    (*buffer)[row + 1][i] = byte; // Записываем в буфер
-                }
-                return false;
-            }
-        }
+fn date_set_action(io: &mut Io, args: &Args) -> i32 {
+    let mut parts = args.positional[0].split('.');
 
-        let msg = b"Invalid date format!";
-        for (i, &byte) in msg.iter().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
+    if let (Some(d), Some(m), Some(y)) = (parts.next(), parts.next(), parts.next()) {
+        if let (Ok(day), Ok(month), Ok(year)) = (d.parse::<u8>(), m.parse::<u8>(), y.parse::<u16>())
+        {
+            set_date(day, month, year);
+            let _ = writeln!(io, "Date set!");
+            return 0;
         }
-        false
     }
+
+    eprintln!("Invalid date format!");
+    1
 }
 
-fn time_set_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let command: &[u8] = &(*buffer)[row][12..20]; // Извлекаем аргументы после `time_add`
+fn time_set_action(io: &mut Io, args: &Args) -> i32 {
+    let mut parts = args.positional[0].split(':');
 
-        let command_str = core::str::from_utf8(command).unwrap_or("").trim();
+    if let (Some(h), Some(m), Some(s)) = (parts.next(), parts.next(), parts.next()) {
+        if let (Ok(hours), Ok(minutes), Ok(seconds)) =
+            (h.parse::<u8>(), m.parse::<u8>(), s.parse::<u8>())
+        {
+            set_time(hours, minutes, seconds);
+            let _ = writeln!(io, "Time set!");
+            return 0;
+        }
+    }
 
-        let mut parts = command_str.split(':');
+    eprintln!("Invalid time format!");
+    1
+}
 
-        if let (Some(h), Some(m), Some(s)) = (parts.next(), parts.next(), parts.next()) {
-            if let (Ok(hours), Ok(minutes), Ok(seconds)) =
-                (h.parse::<u8>(), m.parse::<u8>(), s.parse::<u8>())
-            {
-                set_time(hours, minutes, seconds);
-                let msg = b"Time set!";
-                for (i, &byte) in msg.iter().enumerate() {
-                    write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-                    (*buffer)[row + 1][i] = byte; // Записываем в буфер
-                }
-                return false;
+fn echo_action(io: &mut Io, args: &Args) -> i32 {
+    let _ = write!(io, "{}", args.positional.join(" "));
+    if !args.flag("no-newline") {
+        let _ = writeln!(io);
+    }
+    0
+}
+
+// Текст из файлов, перечисленных в `paths`, или из стандартного ввода,
+// если файлов нет. При ошибке чтения печатает сообщение с именем команды.
+pub fn read_input(name: &str, paths: &[&str], stdin: Option<&str>) -> Option<String> {
+    if paths.is_empty() {
+        return Some(String::from(stdin.unwrap_or("")));
+    }
+
+    let guard = FS.lock();
+    let Some(fs) = guard.as_ref() else {
+        drop(guard);
+        eprintln!("No filesystem mounted");
+        return None;
+    };
+    let mut text = String::new();
+    for path in paths {
+        match fs.read_file(path) {
+            Ok(data) => text.push_str(&String::from_utf8_lossy(data)),
+            Err(e) => {
+                drop(guard);
+                eprintln!("{}: {}: {}", name, path, e.message());
+                return None;
             }
         }
+    }
+    Some(text)
+}
+
+fn grep_action(io: &mut Io, args: &Args) -> i32 {
+    let ignore_case = args.flag("ignore-case");
+    let invert = args.flag("invert-match");
+    let pattern = if ignore_case {
+        args.positional[0].to_lowercase()
+    } else {
+        String::from(args.positional[0])
+    };
+    let Some(text) = read_input("grep", &args.positional[1..], io.stdin) else {
+        return 2;
+    };
 
-        let msg = b"Invalid time format!";
-        for (i, &byte) in msg.iter().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
+    // Как и в Unix: 0 — есть совпадения, 1 — нет
+    let mut found = false;
+    for line in text.lines() {
+        let matches = if ignore_case {
+            line.to_lowercase().contains(pattern.as_str())
+        } else {
+            line.contains(pattern.as_str())
+        };
+        if matches != invert {
+            found = true;
+            let _ = writeln!(io, "{}", line);
         }
-        false
     }
+    if found {
+        0
+    } else {
+        1
+    }
+}
+
+fn wc_action(io: &mut Io, args: &Args) -> i32 {
+    let Some(text) = read_input("wc", &args.positional, io.stdin) else {
+        return 1;
+    };
+    let lines = text.bytes().filter(|&b| b == b'\n').count();
+    let words = text.split_whitespace().count();
+    let _ = writeln!(io, "{:7} {:7} {:7}", lines, words, text.len());
+    0
 }
 
-fn error_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let msg = b"Error: command";
-        for (i, &byte) in msg.iter().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
+fn test_action(_io: &mut Io, args: &Args) -> i32 {
+    // `[ ... ]`: закрывающая скобка не участвует в выражении
+    let mut expr = &args.positional[..];
+    if expr.last() == Some(&"]") {
+        expr = &expr[..expr.len() - 1];
+    }
+    match evaluate_test(expr) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(msg) => {
+            eprintln!("test: {}", msg);
+            2
         }
-        false
     }
 }
 
-fn reboot_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let msg = b"Rebooting...";
-        for (i, &byte) in msg.iter().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
+fn evaluate_test(expr: &[&str]) -> Result<bool, String> {
+    match *expr {
+        [] => Ok(false),
+        ["!", ref rest @ ..] => evaluate_test(rest).map(|result| !result),
+        [text] => Ok(!text.is_empty()),
+        ["-n", text] => Ok(!text.is_empty()),
+        ["-z", text] => Ok(text.is_empty()),
+        [op @ ("-e" | "-f" | "-d"), path] => {
+            let guard = FS.lock();
+            let Some(fs) = guard.as_ref() else {
+                return Ok(false);
+            };
+            let is_file = fs.read_file(path).is_ok();
+            let is_dir = fs.read_dir(path).is_ok();
+            Ok(match op {
+                "-f" => is_file,
+                "-d" => is_dir,
+                _ => is_file || is_dir,
+            })
         }
-
-        asm!(
-            "cli",            // Отключаем прерывания
-            "out 0x64, al",   // Отправляем команду на контроллер клавиатуры
-            "2: hlt",         // Метка 2: останавливаем процессор
-            "jmp 2b",         // Переход к метке 2, чтобы создать бесконечный цикл
-            in("al") 0xFEu8   // Значение 0xFE для команды перезагрузки
-        );
-        false
+        [a, "=" | "==", b] => Ok(a == b),
+        [a, "!=", b] => Ok(a != b),
+        [a, op @ ("-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge"), b] => {
+            let a = parse_number(a)?;
+            let b = parse_number(b)?;
+            Ok(match op {
+                "-eq" => a == b,
+                "-ne" => a != b,
+                "-lt" => a < b,
+                "-le" => a <= b,
+                "-gt" => a > b,
+                _ => a >= b,
+            })
+        }
+        _ => Err(format!("unknown expression: {}", expr.join(" "))),
     }
 }
 
-fn shutdown_action(buffer: *mut [[u8; COLS]; ROWS], row: usize) -> bool {
-    unsafe {
-        let msg = b"Shutting down...";
-        for (i, &byte) in msg.iter().enumerate() {
-            write_char(row + 1, i, byte, 0x07); // Печатает на строке row + 1
-            (*buffer)[row + 1][i] = byte; // Записываем в буфер
+fn parse_number(text: &str) -> Result<i64, String> {
+    text.parse::<i64>()
+        .map_err(|_| format!("integer expected: {}", text))
+}
+
+fn expr_action(io: &mut Io, args: &Args) -> i32 {
+    let [a, op, b] = args.positional[..] else {
+        return 2;
+    };
+    let (a, b) = match (parse_number(a), parse_number(b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(msg), _) | (_, Err(msg)) => {
+            eprintln!("expr: {}", msg);
+            return 2;
         }
+    };
 
-        asm!(
-            "cli",            // Отключаем прерывания
-            "mov ax, 0x5301", // Подключаемся к APM API
-            "xor bx, bx",
-            "int 0x15",
-            "mov ax, 0x530E", // Устанавливаем версию APM на 1.2
-            "xor bx, bx",
-            "mov cx, 0x0102",
-            "int 0x15",
-            "mov ax, 0x5307", // Выключаем систему
-            "mov bx, 0x0001",
-            "mov cx, 0x0003",
-            "int 0x15",
-            "hlt", // Останавливаем процессор
-            options(noreturn, nostack)
-        );
-    }
-}
-
-fn clear(buffer: *mut [[u8; COLS]; ROWS], _: usize) -> bool {
-    let screen_width = 80;
-    let screen_height = 25;
-    clear_screen(screen_width, screen_height);
-
-    unsafe {
-        for row in (*buffer).iter_mut() {
-            for cell in row.iter_mut() {
-                *cell = 0;
-            }
+    let result = match op {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" => a.checked_div(b),
+        "%" => a.checked_rem(b),
+        "=" => Some((a == b) as i64),
+        "!=" => Some((a != b) as i64),
+        "<" => Some((a < b) as i64),
+        "<=" => Some((a <= b) as i64),
+        ">" => Some((a > b) as i64),
+        ">=" => Some((a >= b) as i64),
+        _ => {
+            eprintln!("expr: unknown operator: {}", op);
+            return 2;
         }
-        CURRENT_COL = 0;
-        CURRENT_ROW = 0;
+    };
+    let Some(result) = result else {
+        eprintln!("expr: division by zero or overflow");
+        return 2;
+    };
+
+    let _ = writeln!(io, "{}", result);
+    // Как и в Unix: результат 0 означает «ложь»
+    if result == 0 {
+        1
+    } else {
+        0
     }
+}
 
-    true // Возвращаем true
+fn true_action(_io: &mut Io, _args: &Args) -> i32 {
+    0
 }
 
-pub fn command_fn(buffer: *mut [[u8; COLS]; ROWS], row: usize, command: &String) -> bool {
-    let (cmd, _) = match command.find(' ') {
-        Some(pos) => command.split_at(pos),
-        None => (command.as_str(), ""),
-    };
+fn false_action(_io: &mut Io, _args: &Args) -> i32 {
+    1
+}
 
-    let comm = cmd.trim();
+fn error_action(_io: &mut Io, _args: &Args) -> i32 {
+    eprintln!("Error: command");
+    127
+}
 
-    // Фильтруем только непустые и ненулевые байты
-    let mut comm_filtered: Vec<u8> = Vec::new();
-    for &byte in comm.as_bytes().iter() {
-        if byte != 0 && !byte.is_ascii_whitespace() {
-            comm_filtered.push(byte);
-        }
+fn reboot_action(io: &mut Io, _args: &Args) -> i32 {
+    let _ = writeln!(io, "Rebooting...");
+    #[cfg(feature = "debug-alloc")]
+    crate::heap_debug::report_leaks();
+    if let Err(e) = fs::sync() {
+        eprintln!("reboot: cannot save /etc: {}", e);
     }
+    power::reboot()
+}
 
-    let commands: [Command; 9] = [
-        Command::new("hello", hello_action),
-        Command::new("time", time_action),
-        Command::new("time_set", time_set_action),
-        Command::new("date", date_action),
-        Command::new("date_set", date_set_action),
-        Command::new("error", error_action),
-        Command::new("reboot", reboot_action),
-        Command::new("shutdown", shutdown_action),
-        Command::new("clear", clear),
-    ];
-
-    for cmd in commands.iter() {
-        let cmd_name_bytes: Vec<u8> = cmd.name.bytes().collect();
-
-        if comm_filtered == cmd_name_bytes {
-            let result = (cmd.action)(buffer, row);
-            if result {
-                return true;
+fn shutdown_action(io: &mut Io, _args: &Args) -> i32 {
+    let _ = writeln!(io, "Shutting down...");
+    #[cfg(feature = "debug-alloc")]
+    crate::heap_debug::report_leaks();
+    if let Err(e) = fs::sync() {
+        eprintln!("shutdown: cannot save /etc: {}", e);
+    }
+    acpi::shutdown();
+    eprintln!("shutdown: no supported power-off method");
+    1
+}
+
+// Раскладывает имена по колонкам одинаковой ширины
+pub fn columns(names: &[String]) -> Vec<String> {
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0) + 2;
+    let per_row = (COLS / width).max(1);
+    names
+        .chunks(per_row)
+        .map(|chunk| {
+            let mut line = String::new();
+            for name in chunk {
+                line.push_str(&format!("{:width$}", name, width = width));
             }
-            return false; // Завершите цикл, если команда найдена, но не вернула true
+            String::from(line.trim_end())
+        })
+        .collect()
+}
+
+fn clear(_io: &mut Io, _args: &Args) -> i32 {
+    console::clear();
+    0
+}
+
+// Выполняет строку команды и возвращает код завершения.
+// Вывод начинается со строки, следующей за `row`.
+pub fn command_fn(row: usize, command: &str) -> i32 {
+    console::start_below(row);
+
+    let status = shell::run(command);
+
+    console::finish_line();
+    status
+}
+
+// Запускает одну команду с уже разобранным `argv` и заданными потоками
+pub fn execute(argv: &[&str], stdin: Option<&str>, stdout: &mut dyn Write) -> i32 {
+    let mut io = Io { stdin, stdout };
+
+    // Только перенаправление без команды (`> file`): ничего не выводим
+    let Some(&name) = argv.first() else {
+        return 0;
+    };
+
+    let Some(cmd) = find(name) else {
+        // Путь к файлу или имя `*.sh` запускает скрипт
+        if name.contains('/') || name.ends_with(".sh") {
+            return shell::run_script(name, &argv[1..], io.stdout);
         }
-    }
+        return error_action(&mut io, &Args::default());
+    };
 
-    error_action(buffer, row);
-    false // Возвращаем false, если команда не найдена
+    match parse(argv, &cmd.args) {
+        Ok(args) => (cmd.handler)(&mut io, &args),
+        Err(msg) => {
+            eprintln!("Error: {}", msg);
+            eprintln!("Usage: {}", cmd.usage);
+            2
+        }
+    }
 }
@@ -0,0 +1,127 @@
+use crate::commands::command_names;
+use crate::fs::FS;
+use crate::shell::alias_names;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub enum Completion {
+    None,
+    // Заменить слово, начинающееся с `start`, на `text`
+    Insert { start: usize, text: String },
+    // Вариантов несколько: показать их списком
+    List(Vec<String>),
+}
+
+struct Candidate {
+    word: String,
+    display: String,
+    is_dir: bool,
+}
+
+// Дополнение слова перед курсором. Первое слово команды (в начале строки
+// или после `|`, `;`, `&&`, `||`) дополняется именами команд, остальные —
+// путями в смонтированной файловой системе.
+// `list` означает, что Tab нажат повторно и нужно показать варианты.
+pub fn complete(line: &str, cursor: usize, list: bool) -> Completion {
+    let before = &line[..cursor];
+    let start = before
+        .rfind([' ', '|', ';', '&', '<', '>'])
+        .map(|pos| pos + 1)
+        .unwrap_or(0);
+    let word = &before[start..];
+    let previous = before[..start].trim_end();
+
+    let mut candidates = if previous.is_empty() || previous.ends_with(['|', ';', '&']) {
+        command_candidates(word)
+    } else {
+        path_candidates(word)
+    };
+
+    match candidates.len() {
+        0 => Completion::None,
+        1 => {
+            let candidate = candidates.remove(0);
+            let mut text = candidate.word;
+            text.push(if candidate.is_dir { '/' } else { ' ' });
+            Completion::Insert { start, text }
+        }
+        _ => {
+            let common = common_prefix(&candidates);
+            if common.len() > word.len() {
+                Completion::Insert {
+                    start,
+                    text: String::from(common),
+                }
+            } else if list {
+                Completion::List(candidates.into_iter().map(|c| c.display).collect())
+            } else {
+                Completion::None
+            }
+        }
+    }
+}
+
+fn command_candidates(prefix: &str) -> Vec<Candidate> {
+    let mut names: Vec<String> = command_names()
+        .into_iter()
+        .map(String::from)
+        .chain(alias_names())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|name| Candidate {
+            display: name.clone(),
+            word: name,
+            is_dir: false,
+        })
+        .collect()
+}
+
+fn path_candidates(word: &str) -> Vec<Candidate> {
+    let fs = FS.lock();
+    let Some(fs) = fs.as_ref() else {
+        return Vec::new();
+    };
+
+    let (dir, prefix) = match word.rfind('/') {
+        Some(pos) => word.split_at(pos + 1),
+        None => ("", word),
+    };
+    let Ok(entries) = fs.read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .into_iter()
+        // Скрытые файлы предлагаются, только если префикс начинается с точки
+        .filter(|entry| entry.name.starts_with(prefix))
+        .filter(|entry| prefix.starts_with('.') || !entry.name.starts_with('.'))
+        .map(|entry| Candidate {
+            word: format!("{}{}", dir, entry.name),
+            display: if entry.is_dir {
+                format!("{}/", entry.name)
+            } else {
+                entry.name.clone()
+            },
+            is_dir: entry.is_dir,
+        })
+        .collect()
+}
+
+fn common_prefix(candidates: &[Candidate]) -> &str {
+    let first = candidates[0].word.as_str();
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .bytes()
+            .zip(candidate.word.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+    &first[..len]
+}
@@ -0,0 +1,136 @@
+use crate::constants::{COLS, CURRENT_COL, CURRENT_ROW, ROWS};
+use crate::{serial, theme, vga};
+use core::fmt;
+use neonforge_core::screen::{Display, Grid, Terminal};
+
+// Содержимое экрана: символы и их цвета. Последняя строка — панель состояния.
+pub static mut BUFFER: [[u8; COLS]; ROWS] = [[0; COLS]; ROWS];
+pub static mut COLORS: [[u8; COLS]; ROWS] = [[0; COLS]; ROWS];
+
+// Экран VGA для логики терминала из neonforge-core
+struct Vga;
+
+impl Display for Vga {
+    fn draw(&mut self, row: usize, col: usize, byte: u8, color: u8) {
+        vga::write_char(row, col, byte, color);
+    }
+
+    fn redraw(&mut self, chars: &Grid, colors: &Grid) {
+        vga::clear_screen(COLS as u16, ROWS as u16);
+        vga::print_buffer(chars, colors);
+    }
+}
+
+// Терминал над BUFFER/COLORS с позиции CURRENT_ROW/CURRENT_COL;
+// новая позиция курсора сохраняется обратно
+fn with_terminal<R>(f: impl FnOnce(&mut Terminal<Vga>) -> R) -> R {
+    unsafe {
+        let mut terminal = Terminal {
+            chars: &mut *(&raw mut BUFFER),
+            colors: &mut *(&raw mut COLORS),
+            row: CURRENT_ROW,
+            col: CURRENT_COL,
+            display: Vga,
+        };
+        let result = f(&mut terminal);
+        CURRENT_ROW = terminal.row;
+        CURRENT_COL = terminal.col;
+        result
+    }
+}
+
+// Вывод в терминал с позиции CURRENT_ROW/CURRENT_COL: перенос длинных строк,
+// обработка '\n' и прокрутка. Текст сразу попадает и в BUFFER, и в видеопамять,
+// а в режиме последовательной консоли — ещё и в COM1.
+pub struct Console {
+    color: u8,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console {
+            color: theme::text(),
+        }
+    }
+
+    pub const fn with_color(color: u8) -> Self {
+        Console { color }
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        serial::mirror(s);
+        with_terminal(|terminal| terminal.write_str(s, self.color));
+        Ok(())
+    }
+}
+
+fn new_line() {
+    with_terminal(|terminal| terminal.new_line());
+}
+
+// Сдвигает экран на строку вверх и перерисовывает его
+pub fn scroll() {
+    with_terminal(|terminal| terminal.scroll());
+}
+
+pub fn clear() {
+    with_terminal(|terminal| terminal.clear());
+    vga::clear_screen(COLS as u16, ROWS as u16);
+    serial::mirror("\x1b[2J\x1b[H");
+}
+
+fn set_position(row: usize, col: usize) {
+    unsafe {
+        CURRENT_ROW = row;
+        CURRENT_COL = col;
+    }
+}
+
+// Начинает вывод с начала строки, следующей за `row`
+pub fn start_below(row: usize) {
+    set_position(row, 0);
+    new_line();
+    serial::mirror("\n");
+}
+
+// Переводит строку, если курсор не в начале строки
+pub fn finish_line() {
+    unsafe {
+        if CURRENT_COL != 0 {
+            new_line();
+            serial::mirror("\n");
+        }
+    }
+}
+
+pub fn _print(args: fmt::Arguments) {
+    let _ = fmt::Write::write_fmt(&mut Console::new(), args);
+}
+
+pub fn _eprint(args: fmt::Arguments) {
+    let _ = fmt::Write::write_fmt(&mut Console::with_color(theme::error()), args);
+}
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ($crate::console::_eprint(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    () => ($crate::eprint!("\n"));
+    ($($arg:tt)*) => ($crate::eprint!("{}\n", format_args!($($arg)*)));
+}
@@ -1,7 +1,13 @@
-pub const ROWS: usize = 25;
-pub const COLS: usize = 80;
-pub const MSG: &[u8; 3] = b"$: ";
+pub use neonforge_core::screen::{COLS, ROWS};
+
+pub const MSG: &[u8; 3] = b"$: "; // Приглашение, если PS1 не задана
+pub const MAX_INPUT_LEN: usize = 255; // Максимальная длина строки ввода
+pub const MAX_PROMPT_LEN: usize = 2 * COLS; // Максимальная длина приглашения
+pub const HISTORY_SIZE: usize = 100; // Количество команд в истории
 pub const MAX_LINES: usize = 10; // Максимальное количество строк в логотипе
+pub const CONFIG_PATH: &str = "/etc/neonrc"; // Настройки оболочки
+pub const AUTOEXEC_PATH: &str = "/etc/autoexec.sh"; // Скрипт, выполняемый при загрузке
+pub const MAX_SCRIPT_DEPTH: usize = 16; // Максимальная вложенность скриптов
 
 pub static mut CURRENT_ROW: usize = 0;
 pub static mut CURRENT_COL: usize = 0;
@@ -10,6 +16,26 @@ pub const GPIO_BASE: usize = 0xFE200000; // Адрес для Raspberry Pi 4. Д
 
 pub const COLOR_STATUS_BAR: u8 = 0xee;
 pub const COLOR_INFO: u8 = 0xe0;
+pub const COLOR_TEXT: u8 = 0x07;
+pub const COLOR_ERROR: u8 = 0x0c;
+pub const COLOR_PANIC: u8 = 0x4f; // Белый на красном
+
+pub const TICKS_PER_SECOND: usize = 1000; // Частота PIT (делитель 1193)
+pub const LOG_BUFFER_SIZE: usize = 16 * 1024; // Размер кольцевого буфера журнала ядра
+pub const SERIAL_BAUD: u32 = 38400; // Скорость COM1 по умолчанию
+pub const MAX_BACKTRACE_DEPTH: usize = 16; // Кадров стека на экране паники
+pub const SERIAL_RX_SIZE: usize = 256; // Размер очереди принятых байтов COM1
 
-pub const HEAP_SIZE: usize = 1024 * 1024; // 1 MiB
+pub const PHYSICAL_MEMORY_OFFSET: u64 = 0x4000_0000_0000; // Совпадает с physical-memory-offset в Cargo.toml
+pub const FRAME_SIZE: u64 = 4096; // Размер физического кадра и страницы
+pub const HEAP_START: u64 = 0x4444_4444_0000; // Отдельная область виртуальной памяти под кучу
+pub const HEAP_INITIAL_SIZE: usize = 1024 * 1024; // 1 MiB
+pub const HEAP_MAX_SIZE: usize = 128 * 1024 * 1024; // Предел роста кучи
+pub const HEAP_GROW_STEP: usize = 256 * 1024; // Минимальный прирост кучи
+pub const MMIO_START: u64 = 0x5000_0000_0000; // Окно для регистров устройств
+pub const MMIO_SIZE: u64 = 1024 * 1024 * 1024;
+pub const KERNEL_STACK_ADDRESS: u64 = 0x6000_0000_0000; // Сторожевая страница основного стека (kernel-stack-address в Cargo.toml)
+pub const STACKS_START: u64 = 0x5800_0000_0000; // Область для стеков ядра (`memory::alloc_stack`)
+pub const STACKS_SIZE: u64 = 1024 * 1024 * 1024;
+pub const STACK_SIZE: u64 = 64 * 1024; // Размер каждого стека из `alloc_stack`
 pub const PARTITION_OFFSET: usize = 1048576; // 1 MiБ
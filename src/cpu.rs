@@ -0,0 +1,266 @@
+use crate::commands::{register, Command, Io};
+use crate::{eprintln, log};
+use core::arch::x86_64::__cpuid_count;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, Ordering};
+use neonforge_core::args::{ArgSpec, Args};
+use neonforge_core::cpuid::{self, Cache, CacheKind, Feature, Regs, Signature};
+use spin::Mutex;
+
+const MAX_CACHES: usize = 8;
+
+// Сведения о загрузочном процессоре. Куча при определении не нужна,
+// поэтому `init` можно вызывать первым делом.
+#[derive(Clone, Copy)]
+pub struct CpuInfo {
+    pub vendor: [u8; 12],
+    pub brand: [u8; 48],
+    pub max_leaf: u32,
+    pub max_ext_leaf: u32,
+    pub signature: Signature,
+    pub apic_id: u32,
+    pub cores: u32,   // Ядер в корпусе
+    pub threads: u32, // Логических процессоров в корпусе
+    caches: [Option<Cache>; MAX_CACHES],
+}
+
+impl CpuInfo {
+    pub fn vendor(&self) -> &str {
+        core::str::from_utf8(&self.vendor).unwrap_or("?")
+    }
+
+    pub fn brand(&self) -> &str {
+        let len = self.brand.iter().position(|&b| b == 0).unwrap_or(48);
+        core::str::from_utf8(&self.brand[..len])
+            .unwrap_or("?")
+            .trim()
+    }
+
+    pub fn caches(&self) -> impl Iterator<Item = &Cache> {
+        self.caches.iter().flatten()
+    }
+}
+
+static INFO: Mutex<Option<CpuInfo>> = Mutex::new(None);
+// Бит `Feature as u64` выставлен, если возможность есть
+static FEATURES: AtomicU64 = AtomicU64::new(0);
+
+fn cpuid(leaf: u32, subleaf: u32) -> Regs {
+    let r = __cpuid_count(leaf, subleaf);
+    Regs {
+        eax: r.eax,
+        ebx: r.ebx,
+        ecx: r.ecx,
+        edx: r.edx,
+    }
+}
+
+// Лист, который процессор не поддерживает, читается нулями
+fn leaf(info: &CpuInfo, leaf: u32, subleaf: u32) -> Regs {
+    let max = if leaf >= 0x8000_0000 {
+        info.max_ext_leaf
+    } else {
+        info.max_leaf
+    };
+    if leaf > max {
+        Regs::default()
+    } else {
+        cpuid(leaf, subleaf)
+    }
+}
+
+pub fn has(feature: Feature) -> bool {
+    FEATURES.load(Ordering::Relaxed) & (1 << feature as u64) != 0
+}
+
+pub fn info() -> Option<CpuInfo> {
+    *INFO.lock()
+}
+
+fn detect_features(info: &CpuInfo) -> u64 {
+    Feature::ALL
+        .iter()
+        .filter(|&&feature| {
+            let (number, reg, bit) = feature.location();
+            leaf(info, number, 0).get(reg) & (1 << bit) != 0
+        })
+        .fold(0, |mask, &feature| mask | 1 << feature as u64)
+}
+
+// Intel описывает кэши листом 4, AMD — листом 0x8000001D того же формата
+fn detect_caches(info: &mut CpuInfo) {
+    let number = if &info.vendor == b"AuthenticAMD" || &info.vendor == b"HygonGenuine" {
+        // Лист есть только при поддержке расширений топологии
+        if leaf(info, 0x8000_0001, 0).ecx & (1 << 22) == 0 {
+            return;
+        }
+        0x8000_001D
+    } else {
+        4
+    };
+    for i in 0..MAX_CACHES {
+        match cpuid::decode_cache(leaf(info, number, i as u32)) {
+            Some(cache) => info.caches[i] = Some(cache),
+            None => break,
+        }
+    }
+}
+
+// Число логических процессоров и ядер в корпусе
+fn detect_topology(info: &mut CpuInfo) {
+    let leaf1 = leaf(info, 1, 0);
+    info.apic_id = leaf1.ebx >> 24;
+
+    // Лист 0xB перечисляет уровни топологии: 1 — потоки ядра, 2 — ядра
+    let (mut smt, mut package) = (0, 0);
+    for level in 0..8 {
+        let regs = leaf(info, 0xB, level);
+        match (regs.ecx >> 8) & 0xFF {
+            0 => break,
+            1 => smt = regs.ebx & 0xFFFF,
+            2 => package = regs.ebx & 0xFFFF,
+            _ => {}
+        }
+        info.apic_id = regs.edx;
+    }
+    if smt != 0 && package != 0 {
+        info.threads = package;
+        info.cores = (package / smt).max(1);
+        return;
+    }
+
+    info.threads = if leaf1.edx & (1 << 28) != 0 {
+        ((leaf1.ebx >> 16) & 0xFF).max(1)
+    } else {
+        1
+    };
+    let amd_cores = leaf(info, 0x8000_0008, 0).ecx & 0xFF;
+    let intel_cores = leaf(info, 4, 0).eax >> 26;
+    info.cores = if &info.vendor == b"AuthenticAMD" && info.max_ext_leaf >= 0x8000_0008 {
+        amd_cores + 1
+    } else if info.max_leaf >= 4 {
+        intel_cores + 1
+    } else {
+        info.threads
+    }
+    .min(info.threads);
+}
+
+pub fn init() {
+    let leaf0 = cpuid(0, 0);
+    let mut info = CpuInfo {
+        vendor: cpuid::vendor(leaf0),
+        brand: [0; 48],
+        max_leaf: leaf0.eax,
+        max_ext_leaf: cpuid(0x8000_0000, 0).eax,
+        signature: Signature {
+            family: 0,
+            model: 0,
+            stepping: 0,
+        },
+        apic_id: 0,
+        cores: 1,
+        threads: 1,
+        caches: [None; MAX_CACHES],
+    };
+    // На процессорах без расширенных листов там повторяется последний базовый
+    if info.max_ext_leaf < 0x8000_0000 {
+        info.max_ext_leaf = 0;
+    }
+    info.signature = cpuid::signature(leaf(&info, 1, 0).eax);
+    if info.max_ext_leaf >= 0x8000_0004 {
+        info.brand = cpuid::brand([
+            cpuid(0x8000_0002, 0),
+            cpuid(0x8000_0003, 0),
+            cpuid(0x8000_0004, 0),
+        ]);
+    }
+    detect_topology(&mut info);
+    detect_caches(&mut info);
+    FEATURES.store(detect_features(&info), Ordering::Relaxed);
+    *INFO.lock() = Some(info);
+
+    log::info!(
+        "CPU: {} {}, {} cores / {} threads",
+        info.vendor(),
+        info.brand(),
+        info.cores,
+        info.threads
+    );
+    if !has(Feature::Apic) {
+        log::warn!("CPU: no local APIC");
+    }
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "cpuinfo",
+        aliases: &[],
+        description: "Show processor identification, caches and feature flags",
+        usage: "cpuinfo",
+        args: ArgSpec::NONE,
+        handler: cpuinfo_action,
+    });
+}
+
+fn cache_name(cache: &Cache) -> &'static str {
+    match cache.kind {
+        CacheKind::Data => "d",
+        CacheKind::Instruction => "i",
+        CacheKind::Unified => "",
+    }
+}
+
+fn cpuinfo_action(io: &mut Io, _args: &Args) -> i32 {
+    let Some(info) = info() else {
+        eprintln!("cpuinfo: CPU not detected");
+        return 1;
+    };
+    let _ = writeln!(io, "vendor_id   : {}", info.vendor());
+    let _ = writeln!(io, "model name  : {}", info.brand());
+    let _ = writeln!(io, "cpu family  : {}", info.signature.family);
+    let _ = writeln!(io, "model       : {}", info.signature.model);
+    let _ = writeln!(io, "stepping    : {}", info.signature.stepping);
+    let _ = writeln!(
+        io,
+        "cpuid level : {:#x}, extended {:#x}",
+        info.max_leaf, info.max_ext_leaf
+    );
+    let _ = writeln!(io, "apicid      : {}", info.apic_id);
+    let _ = writeln!(io, "cpu cores   : {}", info.cores);
+    let _ = writeln!(io, "siblings    : {}", info.threads);
+    for cache in info.caches() {
+        let _ = writeln!(
+            io,
+            "cache L{}{:<2}  : {} KiB, {}-way, {} B line, shared by {}",
+            cache.level,
+            cache_name(cache),
+            cache.size / 1024,
+            cache.ways,
+            cache.line,
+            cache.shared_by
+        );
+    }
+    let _ = write!(io, "flags       :");
+    for feature in Feature::ALL.iter().filter(|&&feature| has(feature)) {
+        let _ = write!(io, " {}", feature.name());
+    }
+    let _ = writeln!(io);
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has, info};
+    use neonforge_core::cpuid::Feature;
+
+    #[test_case]
+    fn detected_at_boot() {
+        let info = info().unwrap();
+        assert!(info.max_leaf >= 1);
+        assert!(info.cores >= 1 && info.threads >= info.cores);
+        // Без длинного режима ядро не загрузилось бы
+        assert!(has(Feature::LongMode));
+        assert!(has(Feature::Fpu));
+    }
+}
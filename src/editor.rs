@@ -0,0 +1,132 @@
+use crate::constants::MAX_INPUT_LEN;
+use alloc::string::String;
+
+// Строка ввода командной оболочки с курсором. Хранит только ASCII,
+// поэтому позиция курсора совпадает с байтовым индексом.
+pub struct LineEditor {
+    line: String,
+    cursor: usize,
+    insert_mode: bool,
+}
+
+impl LineEditor {
+    pub const fn new() -> Self {
+        LineEditor {
+            line: String::new(),
+            cursor: 0,
+            insert_mode: true,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.line
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn insert_mode(&self) -> bool {
+        self.insert_mode
+    }
+
+    pub fn toggle_insert_mode(&mut self) {
+        self.insert_mode = !self.insert_mode;
+    }
+
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+    }
+
+    // Заменяет строку целиком (например, записью из истории)
+    pub fn set(&mut self, line: &str) {
+        self.line.clear();
+        for character in line.chars().filter(|c| c.is_ascii()).take(MAX_INPUT_LEN) {
+            self.line.push(character);
+        }
+        self.cursor = self.line.len();
+    }
+
+    pub fn put(&mut self, character: char) {
+        if !character.is_ascii() {
+            return;
+        }
+        if !self.insert_mode && self.cursor < self.line.len() {
+            // Режим замены
+            let mut buf = [0u8; 4];
+            self.line.replace_range(
+                self.cursor..self.cursor + 1,
+                character.encode_utf8(&mut buf),
+            );
+            self.cursor += 1;
+        } else if self.line.len() < MAX_INPUT_LEN {
+            self.line.insert(self.cursor, character);
+            self.cursor += 1;
+        }
+    }
+
+    // Заменяет текст от `start` до курсора (используется дополнением по Tab)
+    pub fn replace_before_cursor(&mut self, start: usize, text: &str) {
+        let room = MAX_INPUT_LEN - (self.line.len() - (self.cursor - start));
+        let text = &text[..text.len().min(room)];
+        self.line.replace_range(start..self.cursor, text);
+        self.cursor = start + text.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.line.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.line.len() {
+            self.line.remove(self.cursor);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        if self.cursor < self.line.len() {
+            self.cursor += 1;
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.line.len();
+    }
+
+    // Ctrl+K: удалить от курсора до конца строки
+    pub fn kill_to_end(&mut self) {
+        self.line.truncate(self.cursor);
+    }
+
+    // Ctrl+U: удалить от начала строки до курсора
+    pub fn kill_to_start(&mut self) {
+        self.line.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    // Ctrl+W: удалить слово перед курсором (вместе с пробелами после него)
+    pub fn kill_word_back(&mut self) {
+        let bytes = self.line.as_bytes();
+        let mut start = self.cursor;
+        while start > 0 && bytes[start - 1] == b' ' {
+            start -= 1;
+        }
+        while start > 0 && bytes[start - 1] != b' ' {
+            start -= 1;
+        }
+        self.line.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+}
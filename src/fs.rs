@@ -0,0 +1,517 @@
+use crate::ata::{self, Drive, SECTOR_SIZE};
+use crate::commands::{columns, read_input, register, Command, Io};
+use crate::constants::{AUTOEXEC_PATH, CONFIG_PATH};
+use crate::shell::get_var;
+use crate::{eprintln, log};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use neonforge_core::archive;
+use neonforge_core::args::{ArgSpec, Args, OptSpec};
+use spin::Mutex;
+
+// Файловая система в оперативной памяти, монтируется как корневой том при
+// загрузке. Файлы /etc сохраняются архивом на отдельный диск ATA (не на
+// загрузочный): при загрузке они читаются оттуда, `sync` записывает их обратно.
+pub static FS: Mutex<Option<FileSystem>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsError {
+    NotFound,
+    NotADirectory,
+    IsADirectory,
+    AlreadyExists,
+    NotEmpty,
+}
+
+impl FsError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            FsError::NotFound => "No such file or directory",
+            FsError::NotADirectory => "Not a directory",
+            FsError::IsADirectory => "Is a directory",
+            FsError::AlreadyExists => "File exists",
+            FsError::NotEmpty => "Directory not empty",
+        }
+    }
+}
+
+enum Node {
+    File(Vec<u8>),
+    Dir(BTreeMap<String, Node>),
+}
+
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+pub struct FileSystem {
+    root: Node,
+    cwd: String,
+}
+
+impl FileSystem {
+    fn new() -> Self {
+        FileSystem {
+            root: Node::Dir(BTreeMap::new()),
+            cwd: String::from("/"),
+        }
+    }
+
+    // Превращает путь (абсолютный или относительно текущего каталога)
+    // в абсолютный, раскрывая `.` и `..`
+    pub fn resolve(&self, path: &str) -> String {
+        let mut parts: Vec<&str> = Vec::new();
+        let base = if path.starts_with('/') {
+            ""
+        } else {
+            self.cwd.as_str()
+        };
+        for part in base.split('/').chain(path.split('/')) {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop();
+                }
+                name => parts.push(name),
+            }
+        }
+
+        let mut result = String::new();
+        for part in parts {
+            result.push('/');
+            result.push_str(part);
+        }
+        if result.is_empty() {
+            result.push('/');
+        }
+        result
+    }
+
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    pub fn set_cwd(&mut self, path: &str) -> Result<(), FsError> {
+        let full = self.resolve(path);
+        match self.node(&full) {
+            Some(Node::Dir(_)) => {
+                self.cwd = full;
+                Ok(())
+            }
+            Some(Node::File(_)) => Err(FsError::NotADirectory),
+            None => Err(FsError::NotFound),
+        }
+    }
+
+    fn node(&self, path: &str) -> Option<&Node> {
+        let mut node = &self.root;
+        for part in self.resolve(path).split('/').filter(|p| !p.is_empty()) {
+            match node {
+                Node::Dir(children) => node = children.get(part)?,
+                Node::File(_) => return None,
+            }
+        }
+        Some(node)
+    }
+
+    // Каталог, в котором лежит последний компонент пути, и имя этого компонента
+    fn parent_mut(&mut self, path: &str) -> Result<(&mut BTreeMap<String, Node>, String), FsError> {
+        let full = self.resolve(path);
+        let (parent, name) = full.rsplit_once('/').unwrap_or(("", full.as_str()));
+        if name.is_empty() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        let mut node = &mut self.root;
+        for part in parent.split('/').filter(|p| !p.is_empty()) {
+            match node {
+                Node::Dir(children) => node = children.get_mut(part).ok_or(FsError::NotFound)?,
+                Node::File(_) => return Err(FsError::NotADirectory),
+            }
+        }
+        match node {
+            Node::Dir(children) => Ok((children, String::from(name))),
+            Node::File(_) => Err(FsError::NotADirectory),
+        }
+    }
+
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, FsError> {
+        match self.node(path) {
+            Some(Node::Dir(children)) => Ok(children
+                .iter()
+                .map(|(name, node)| DirEntry {
+                    name: name.clone(),
+                    is_dir: matches!(node, Node::Dir(_)),
+                })
+                .collect()),
+            Some(Node::File(_)) => Err(FsError::NotADirectory),
+            None => Err(FsError::NotFound),
+        }
+    }
+
+    pub fn create_dir(&mut self, path: &str) -> Result<(), FsError> {
+        let (children, name) = self.parent_mut(path)?;
+        if children.contains_key(&name) {
+            return Err(FsError::AlreadyExists);
+        }
+        children.insert(name, Node::Dir(BTreeMap::new()));
+        Ok(())
+    }
+
+    pub fn read_file(&self, path: &str) -> Result<&[u8], FsError> {
+        match self.node(path) {
+            Some(Node::File(content)) => Ok(content),
+            Some(Node::Dir(_)) => Err(FsError::IsADirectory),
+            None => Err(FsError::NotFound),
+        }
+    }
+
+    // Создаёт файл или перезаписывает существующий
+    pub fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        let content = self.open_file(path)?;
+        content.clear();
+        content.extend_from_slice(data);
+        Ok(())
+    }
+
+    // Дописывает данные в конец файла, создавая его при необходимости
+    pub fn append_file(&mut self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        self.open_file(path)?.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn open_file(&mut self, path: &str) -> Result<&mut Vec<u8>, FsError> {
+        let (children, name) = self.parent_mut(path)?;
+        match children
+            .entry(name)
+            .or_insert_with(|| Node::File(Vec::new()))
+        {
+            Node::File(content) => Ok(content),
+            Node::Dir(_) => Err(FsError::IsADirectory),
+        }
+    }
+
+    // Удаляет файл или пустой каталог
+    pub fn remove(&mut self, path: &str) -> Result<(), FsError> {
+        let (children, name) = self.parent_mut(path)?;
+        match children.get(&name) {
+            None => return Err(FsError::NotFound),
+            Some(Node::Dir(entries)) if !entries.is_empty() => return Err(FsError::NotEmpty),
+            Some(_) => {}
+        }
+        children.remove(&name);
+        Ok(())
+    }
+}
+
+pub fn mount() {
+    let mut fs = FileSystem::new();
+    for dir in ["/etc", "/home", "/tmp"] {
+        let _ = fs.create_dir(dir);
+    }
+    let files: [(&str, &[u8]); 3] = [
+        ("/etc/hostname", b"neonforge\n"),
+        (CONFIG_PATH, include_bytes!("../rootfs/etc/neonrc")),
+        (AUTOEXEC_PATH, include_bytes!("../rootfs/etc/autoexec.sh")),
+    ];
+    for (path, data) in files {
+        if let Err(e) = fs.write_file(path, data) {
+            log::error!("Cannot create {}: {}", path, e.message());
+        }
+    }
+    if let Some(disk) = config_disk() {
+        load_etc(&mut fs, &disk);
+    }
+    *FS.lock() = Some(fs);
+}
+
+const ETC: &str = "/etc";
+const CHUNK_SECTORS: usize = 128; // Секторов за одну команду диска
+
+// Диск для /etc: первый незагрузочный, на котором уже есть архив или
+// который ещё пуст (нулевой первый сектор)
+pub fn config_disk() -> Option<Drive> {
+    ata::drives().into_iter().find(|drive| {
+        let mut sector = [0; SECTOR_SIZE];
+        !drive.is_boot_disk()
+            && drive.read(0, &mut sector).is_ok()
+            && (archive::packed_len(&sector).is_some() || sector.iter().all(|&b| b == 0))
+    })
+}
+
+fn load_etc(fs: &mut FileSystem, disk: &Drive) {
+    let mut sector = [0; SECTOR_SIZE];
+    let Some(len) = disk
+        .read(0, &mut sector)
+        .ok()
+        .and_then(|_| archive::packed_len(&sector))
+    else {
+        log::info!("{}: no saved /etc, using built-in files", disk.name());
+        return;
+    };
+    if len.div_ceil(SECTOR_SIZE) > disk.sectors as usize {
+        log::error!("{}: saved /etc is larger than the disk", disk.name());
+        return;
+    }
+
+    let mut data = alloc::vec![0; len.div_ceil(SECTOR_SIZE) * SECTOR_SIZE];
+    for (i, chunk) in data.chunks_mut(CHUNK_SECTORS * SECTOR_SIZE).enumerate() {
+        if let Err(e) = disk.read((i * CHUNK_SECTORS) as u32, chunk) {
+            log::error!("{}: cannot read /etc: {}", disk.name(), e);
+            return;
+        }
+    }
+    let Some(files) = archive::unpack(&data) else {
+        log::error!(
+            "{}: saved /etc is damaged, using built-in files",
+            disk.name()
+        );
+        return;
+    };
+    for (name, content) in &files {
+        let path = format!("{}/{}", ETC, name);
+        if let Err(e) = fs.write_file(&path, content) {
+            log::error!("Cannot restore {}: {}", path, e.message());
+        }
+    }
+    log::info!("{}: restored {} files in /etc", disk.name(), files.len());
+}
+
+// Записывает файлы /etc на диск. Ok(None) — подходящего диска нет.
+pub fn sync() -> Result<Option<Drive>, &'static str> {
+    let Some(disk) = config_disk() else {
+        return Ok(None);
+    };
+    let files: Vec<(String, Vec<u8>)> = {
+        let guard = FS.lock();
+        let fs = guard.as_ref().ok_or("filesystem not mounted")?;
+        let entries = fs.read_dir(ETC).map_err(|e| e.message())?;
+        entries
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .filter_map(|entry| {
+                let content = fs.read_file(&format!("{}/{}", ETC, entry.name)).ok()?;
+                Some((entry.name, content.to_vec()))
+            })
+            .collect()
+    };
+
+    let mut data = archive::pack(&files);
+    data.resize(data.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE, 0);
+    if data.len() / SECTOR_SIZE > disk.sectors as usize {
+        return Err("disk is too small");
+    }
+    // Заголовок в первом секторе ссылается на остальные, поэтому он пишется последним
+    let chunks: Vec<&[u8]> = data.chunks(CHUNK_SECTORS * SECTOR_SIZE).collect();
+    for (i, chunk) in chunks.iter().enumerate().rev() {
+        disk.write((i * CHUNK_SECTORS) as u32, chunk)?;
+    }
+    Ok(Some(disk))
+}
+
+pub fn init() {
+    register(Command {
+        name: "ls",
+        aliases: &["dir"],
+        description: "List directory contents",
+        usage: "ls [-a|--all] [PATH]",
+        args: ArgSpec {
+            options: &[OptSpec {
+                name: "all",
+                short: Some('a'),
+                takes_value: false,
+            }],
+            min: 0,
+            max: 1,
+        },
+        handler: ls_action,
+    });
+    register(Command {
+        name: "cd",
+        aliases: &[],
+        description: "Change the current directory",
+        usage: "cd [DIR]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: 1,
+        },
+        handler: cd_action,
+    });
+    register(Command {
+        name: "pwd",
+        aliases: &[],
+        description: "Print the current directory",
+        usage: "pwd",
+        args: ArgSpec::NONE,
+        handler: pwd_action,
+    });
+    register(Command {
+        name: "cat",
+        aliases: &[],
+        description: "Print files or standard input",
+        usage: "cat [FILE...]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: cat_action,
+    });
+    register(Command {
+        name: "mkdir",
+        aliases: &["md"],
+        description: "Create directories",
+        usage: "mkdir DIR...",
+        args: ArgSpec {
+            options: &[],
+            min: 1,
+            max: usize::MAX,
+        },
+        handler: mkdir_action,
+    });
+    register(Command {
+        name: "rm",
+        aliases: &["del"],
+        description: "Remove files or empty directories",
+        usage: "rm PATH...",
+        args: ArgSpec {
+            options: &[],
+            min: 1,
+            max: usize::MAX,
+        },
+        handler: rm_action,
+    });
+    register(Command {
+        name: "sync",
+        aliases: &[],
+        description: "Save /etc to the configuration disk",
+        usage: "sync",
+        args: ArgSpec::NONE,
+        handler: sync_action,
+    });
+}
+
+fn ls_action(io: &mut Io, args: &Args) -> i32 {
+    let path = args.positional.first().copied().unwrap_or("");
+    let all = args.flag("all");
+
+    let guard = FS.lock();
+    let Some(fs) = guard.as_ref() else {
+        drop(guard);
+        eprintln!("No filesystem mounted");
+        return 1;
+    };
+    let result = fs.read_dir(path);
+    drop(guard);
+
+    match result {
+        Ok(entries) => {
+            let names: Vec<String> = entries
+                .iter()
+                .filter(|entry| all || !entry.name.starts_with('.'))
+                .map(|entry| {
+                    if entry.is_dir {
+                        format!("{}/", entry.name)
+                    } else {
+                        entry.name.clone()
+                    }
+                })
+                .collect();
+            for line in columns(&names) {
+                let _ = writeln!(io, "{}", line);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("ls: {}: {}", path, e.message());
+            1
+        }
+    }
+}
+
+fn cd_action(_io: &mut Io, args: &Args) -> i32 {
+    // Без аргумента — в домашний каталог
+    let home = get_var("HOME").unwrap_or_else(|| String::from("/"));
+    let path = args.positional.first().copied().unwrap_or(home.as_str());
+
+    let result = match FS.lock().as_mut() {
+        Some(fs) => fs.set_cwd(path).map_err(|e| e.message()),
+        None => Err("No filesystem mounted"),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(msg) => {
+            eprintln!("cd: {}: {}", path, msg);
+            1
+        }
+    }
+}
+
+fn pwd_action(io: &mut Io, _args: &Args) -> i32 {
+    let cwd = match FS.lock().as_ref() {
+        Some(fs) => String::from(fs.cwd()),
+        None => String::from("/"),
+    };
+    let _ = writeln!(io, "{}", cwd);
+    0
+}
+
+fn cat_action(io: &mut Io, args: &Args) -> i32 {
+    match read_input("cat", &args.positional, io.stdin) {
+        Some(text) => {
+            let _ = write!(io, "{}", text);
+            0
+        }
+        None => 1,
+    }
+}
+
+// Применяет `op` к каждому пути; ошибки печатаются, но не прерывают работу
+fn for_each_path(
+    name: &str,
+    args: &Args,
+    op: fn(&mut FileSystem, &str) -> Result<(), FsError>,
+) -> i32 {
+    let mut status = 0;
+    for path in &args.positional {
+        let result = match FS.lock().as_mut() {
+            Some(fs) => op(fs, path).map_err(|e| e.message()),
+            None => Err("No filesystem mounted"),
+        };
+        if let Err(msg) = result {
+            eprintln!("{}: {}: {}", name, path, msg);
+            status = 1;
+        }
+    }
+    status
+}
+
+fn mkdir_action(_io: &mut Io, args: &Args) -> i32 {
+    for_each_path("mkdir", args, FileSystem::create_dir)
+}
+
+fn rm_action(_io: &mut Io, args: &Args) -> i32 {
+    for_each_path("rm", args, FileSystem::remove)
+}
+
+fn sync_action(io: &mut Io, _args: &Args) -> i32 {
+    match sync() {
+        Ok(Some(disk)) => {
+            let _ = writeln!(io, "/etc saved to {}", disk.name());
+            0
+        }
+        Ok(None) => {
+            eprintln!("sync: no configuration disk");
+            1
+        }
+        Err(e) => {
+            eprintln!("sync: {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::memory;
+use spin::Once;
+use x86_64::instructions::segmentation::{Segment, CS, DS, ES, SS};
+use x86_64::instructions::tables::load_tss;
+use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
+use x86_64::structures::tss::TaskStateSegment;
+
+// Индекс в IST стека для двойной ошибки: при переполнении основного стека
+// процессор не может положить кадр исключения страницы на сам стек
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
+static TSS: Once<TaskStateSegment> = Once::new();
+static GDT: Once<(GlobalDescriptorTable, Selectors)> = Once::new();
+
+struct Selectors {
+    code: SegmentSelector,
+    data: SegmentSelector,
+    tss: SegmentSelector,
+}
+
+// Заменяет GDT загрузчика на собственную с сегментом TSS.
+// Вызывается после `memory::init`: стек двойной ошибки отображается заново.
+pub fn init() {
+    let stack = memory::alloc_stack().expect("no memory for the double fault stack");
+    let tss = TSS.call_once(|| {
+        let mut tss = TaskStateSegment::new();
+        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = stack;
+        tss
+    });
+    let (gdt, selectors) = GDT.call_once(|| {
+        let mut gdt = GlobalDescriptorTable::new();
+        let selectors = Selectors {
+            code: gdt.add_entry(Descriptor::kernel_code_segment()),
+            data: gdt.add_entry(Descriptor::kernel_data_segment()),
+            tss: gdt.add_entry(Descriptor::tss_segment(tss)),
+        };
+        (gdt, selectors)
+    });
+    gdt.load();
+
+    unsafe {
+        CS::set_reg(selectors.code);
+        SS::set_reg(selectors.data);
+        DS::set_reg(selectors.data);
+        ES::set_reg(selectors.data);
+        load_tss(selectors.tss);
+    }
+}
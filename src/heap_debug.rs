@@ -0,0 +1,301 @@
+use crate::allocator::ALLOCATOR;
+use crate::commands::{register, Command, Io};
+use crate::log;
+use crate::panic::StackFrames;
+use core::alloc::Layout;
+use core::fmt::Write;
+use core::mem::size_of;
+use core::ptr;
+use neonforge_core::args::{ArgSpec, Args};
+
+// Отладочный режим кучи (функция `debug-alloc`). Каждое размещение окружено
+// служебными данными:
+//
+//   [место под узел списка кучи][заголовок][красная зона][данные][красная зона]
+//
+// Заголовок хранит размер, номер и адреса вызова, а также ссылки в списке
+// живых размещений, по которому ищутся утечки.
+
+const ALLOC_MAGIC: u64 = 0xA110_CA7E_D0D0_CAFE;
+const FREED_MAGIC: u64 = 0xF4EE_D0D0_DEAD_BEEF;
+const REDZONE: usize = 16;
+const REDZONE_BYTE: u8 = 0xFD;
+const POISON_NEW: u8 = 0xCD; // Выделенная, но ещё не записанная память
+const POISON_FREED: u8 = 0xDD; // Освобождённая память
+const CALL_SITES: usize = 6;
+// Освобождённый блок кучи хранит свой размер и ссылку в первых байтах,
+// заголовок лежит после них и переживает освобождение
+const LIST_NODE_SPACE: usize = 16;
+const MIN_ALIGN: usize = 16;
+const MAX_LEAK_REPORT: usize = 32;
+
+#[repr(C)]
+struct Header {
+    magic: u64,
+    size: usize,
+    seq: u64,
+    prev: *mut Header,
+    next: *mut Header,
+    sites: [u64; CALL_SITES],
+}
+
+// Список живых размещений; хранится в состоянии кучи под её блокировкой
+pub struct Tracker {
+    live: *mut Header,
+    seq: u64,
+    checkpoint: u64, // Размещения с меньшими номерами сделаны при загрузке
+}
+
+// Указатели ведут в саму кучу и используются только под её блокировкой
+unsafe impl Send for Tracker {}
+
+#[derive(Clone, Copy)]
+struct Leak {
+    address: usize,
+    size: usize,
+    sites: [u64; CALL_SITES],
+    corrupted: bool,
+}
+
+fn prefix(align: usize) -> usize {
+    (LIST_NODE_SPACE + size_of::<Header>() + REDZONE).next_multiple_of(align)
+}
+
+// Размещение, которое запрашивается у кучи вместо пользовательского
+pub fn outer_layout(layout: Layout) -> Layout {
+    let align = layout.align().max(MIN_ALIGN);
+    Layout::from_size_align(prefix(align) + layout.size() + REDZONE, align).unwrap()
+}
+
+unsafe fn header_of(user: *mut u8) -> *mut Header {
+    user.sub(REDZONE + size_of::<Header>()) as *mut Header
+}
+
+// Красные зоны вокруг данных не тронуты
+unsafe fn redzones_intact(user: *mut u8, size: usize) -> bool {
+    let front = user.sub(REDZONE);
+    let back = user.add(size);
+    (0..REDZONE).all(|i| *front.add(i) == REDZONE_BYTE && *back.add(i) == REDZONE_BYTE)
+}
+
+impl Tracker {
+    pub const fn new() -> Self {
+        Tracker {
+            live: ptr::null_mut(),
+            seq: 0,
+            checkpoint: 0,
+        }
+    }
+
+    // Размечает блок `base`, выделенный по `outer_layout(layout)`, и возвращает
+    // адрес данных
+    #[inline(always)]
+    pub unsafe fn on_alloc(&mut self, base: *mut u8, layout: Layout) -> *mut u8 {
+        let user = base.add(prefix(layout.align().max(MIN_ALIGN)));
+        let header = header_of(user);
+
+        // Первый адрес возврата ведёт в обёртку `__rust_alloc`, пропускаем его
+        let mut sites = [0; CALL_SITES];
+        for (site, address) in sites.iter_mut().zip(StackFrames::current().skip(1)) {
+            *site = address;
+        }
+
+        header.write(Header {
+            magic: ALLOC_MAGIC,
+            size: layout.size(),
+            seq: self.seq,
+            prev: ptr::null_mut(),
+            next: self.live,
+            sites,
+        });
+        if !self.live.is_null() {
+            (*self.live).prev = header;
+        }
+        self.live = header;
+        self.seq += 1;
+
+        user.sub(REDZONE).write_bytes(REDZONE_BYTE, REDZONE);
+        user.write_bytes(POISON_NEW, layout.size());
+        user.add(layout.size()).write_bytes(REDZONE_BYTE, REDZONE);
+        user
+    }
+
+    // Проверяет освобождаемое размещение и возвращает начало блока кучи.
+    // Ошибки приводят к панике: куча уже повреждена.
+    pub unsafe fn on_free(&mut self, user: *mut u8, layout: Layout) -> *mut u8 {
+        let header = header_of(user);
+        match (*header).magic {
+            ALLOC_MAGIC => {}
+            FREED_MAGIC => panic!("double free of {:p} ({} bytes)", user, layout.size()),
+            _ => panic!(
+                "free of {:p} ({} bytes): not allocated or header overwritten",
+                user,
+                layout.size()
+            ),
+        }
+        if (*header).size != layout.size() {
+            panic!(
+                "free of {:p} with size {}, but {} bytes were allocated",
+                user,
+                layout.size(),
+                (*header).size
+            );
+        }
+        if !redzones_intact(user, layout.size()) {
+            panic!(
+                "heap buffer overrun around {:p} ({} bytes), allocated from {:#x?}",
+                user,
+                layout.size(),
+                (*header).sites
+            );
+        }
+
+        let (prev, next) = ((*header).prev, (*header).next);
+        if prev.is_null() {
+            self.live = next;
+        } else {
+            (*prev).next = next;
+        }
+        if !next.is_null() {
+            (*next).prev = prev;
+        }
+        (*header).magic = FREED_MAGIC;
+        user.write_bytes(POISON_FREED, layout.size());
+        user.sub(prefix(layout.align().max(MIN_ALIGN)))
+    }
+
+    // Живые размещения, сделанные после контрольной точки, и все размещения
+    // с испорченными красными зонами
+    fn leaks(&self, out: &mut [Leak]) -> (usize, usize) {
+        let (mut found, mut total) = (0, 0);
+        let mut header = self.live;
+        while !header.is_null() {
+            unsafe {
+                let user = (header as *mut u8).add(size_of::<Header>() + REDZONE);
+                let corrupted = !redzones_intact(user, (*header).size);
+                if (*header).seq >= self.checkpoint || corrupted {
+                    if found < out.len() {
+                        out[found] = Leak {
+                            address: user as usize,
+                            size: (*header).size,
+                            sites: (*header).sites,
+                            corrupted,
+                        };
+                        found += 1;
+                    }
+                    total += 1;
+                }
+                header = (*header).next;
+            }
+        }
+        (found, total)
+    }
+}
+
+// Всё, что выделено до этого момента, считается данными ядра, а не утечкой
+pub fn checkpoint() {
+    let mut heap = ALLOCATOR.lock();
+    heap.tracker.checkpoint = heap.tracker.seq;
+}
+
+// Выводит в журнал размещения, не освобождённые с контрольной точки.
+// Возвращает их количество.
+pub fn report_leaks() -> usize {
+    let mut leaks = [Leak {
+        address: 0,
+        size: 0,
+        sites: [0; CALL_SITES],
+        corrupted: false,
+    }; MAX_LEAK_REPORT];
+    // Журнал пишется без блокировки кучи
+    let (found, total) = ALLOCATOR.lock().tracker.leaks(&mut leaks);
+    for leak in &leaks[..found] {
+        let sites = leak.sites.iter().take_while(|&&site| site != 0);
+        let mut line = [0u8; 128];
+        let mut writer = LineWriter {
+            buf: &mut line,
+            len: 0,
+        };
+        for site in sites {
+            let _ = write!(writer, " {:#x}", site);
+        }
+        let len = writer.len;
+        let sites = core::str::from_utf8(&line[..len]).unwrap_or("");
+        if leak.corrupted {
+            log::error!(
+                "heap overrun: {} bytes at {:#x}, allocated from{}",
+                leak.size,
+                leak.address,
+                sites
+            );
+        } else {
+            log::warn!(
+                "leak: {} bytes at {:#x}, allocated from{}",
+                leak.size,
+                leak.address,
+                sites
+            );
+        }
+    }
+    if total > found {
+        log::warn!("... and {} more", total - found);
+    }
+    total
+}
+
+// Форматирование адресов в буфер на стеке: отчёт не должен выделять память
+struct LineWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for LineWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "leaks",
+        aliases: &[],
+        description: "Report heap allocations made since boot that are still live",
+        usage: "leaks",
+        args: ArgSpec::NONE,
+        handler: leaks_action,
+    });
+}
+
+fn leaks_action(io: &mut Io, _args: &Args) -> i32 {
+    let total = report_leaks();
+    let _ = writeln!(io, "{} live allocations since boot (see dmesg)", total);
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{POISON_FREED, POISON_NEW, REDZONE, REDZONE_BYTE};
+    use alloc::alloc::{alloc, dealloc};
+    use core::alloc::Layout;
+
+    #[test_case]
+    fn poison_and_redzones() {
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        unsafe {
+            let ptr = alloc(layout);
+            assert_eq!(ptr as usize % 16, 0);
+            assert!((0..24).all(|i| *ptr.add(i) == POISON_NEW));
+            assert_eq!(*ptr.sub(1), REDZONE_BYTE);
+            assert_eq!(*ptr.add(24 + REDZONE - 1), REDZONE_BYTE);
+            dealloc(ptr, layout);
+            // Узлы свободных списков лежат в начале блока, данные после освобождения затёрты
+            assert!((0..24).all(|i| *ptr.add(i) == POISON_FREED));
+        }
+    }
+}
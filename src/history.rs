@@ -0,0 +1,294 @@
+use crate::commands::{register, Command, Io};
+use crate::constants::HISTORY_SIZE;
+use crate::eprintln;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+use neonforge_core::args::{ArgSpec, Args, OptSpec};
+use spin::Mutex;
+
+pub static HISTORY: Mutex<History> = Mutex::new(History::new());
+
+// Кольцо последних введённых команд. Номера записей сквозные (как в bash),
+// поэтому `!n` продолжает работать после вытеснения старых записей.
+pub struct History {
+    entries: VecDeque<String>,
+    first_number: usize,
+    browse: Option<usize>,
+    draft: String,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        History {
+            entries: VecDeque::new(),
+            first_number: 1,
+            browse: None,
+            draft: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || self.entries.back().map(|last| last.as_str()) == Some(line) {
+            return;
+        }
+        if self.entries.len() == HISTORY_SIZE {
+            self.entries.pop_front();
+            self.first_number += 1;
+        }
+        self.entries.push_back(String::from(line));
+    }
+
+    pub fn clear(&mut self) {
+        self.first_number += self.entries.len();
+        self.entries.clear();
+        self.reset_browse();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Возвращает записи вместе с их номерами
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(move |(i, entry)| (self.first_number + i, entry.as_str()))
+    }
+
+    pub fn get(&self, number: usize) -> Option<&str> {
+        let index = number.checked_sub(self.first_number)?;
+        self.entries.get(index).map(|entry| entry.as_str())
+    }
+
+    pub fn entry(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(|entry| entry.as_str())
+    }
+
+    // Стрелка вверх: более старая запись. Текущий ввод запоминается,
+    // чтобы вернуть его стрелкой вниз.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let index = match self.browse {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = String::from(current);
+                self.entries.len() - 1
+            }
+            Some(0) => 0,
+            Some(index) => index - 1,
+        };
+        self.browse = Some(index);
+        self.entry(index)
+    }
+
+    // Стрелка вниз: более новая запись или сохранённый ввод
+    pub fn next(&mut self) -> Option<&str> {
+        let index = self.browse?;
+        if index + 1 < self.entries.len() {
+            self.browse = Some(index + 1);
+            self.entry(index + 1)
+        } else {
+            self.browse = None;
+            Some(&self.draft)
+        }
+    }
+
+    pub fn reset_browse(&mut self) {
+        self.browse = None;
+        self.draft.clear();
+    }
+
+    // Индекс самой новой записи, содержащей `query`, среди записей до `before`
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        let end = before.min(self.entries.len());
+        (0..end)
+            .rev()
+            .find(|&index| self.entries[index].contains(query))
+    }
+
+    // Подстановка `!!`, `!n` и `!-n`. Внутри одинарных кавычек, как в bash,
+    // `!` остаётся как есть.
+    pub fn expand(&self, line: &str) -> Result<String, String> {
+        let mut result = String::new();
+        let mut rest = line;
+        let mut quote = None; // Открытая кавычка
+
+        while let Some(pos) = rest.find(['!', '\'', '"']) {
+            result.push_str(&rest[..pos]);
+            let c = rest.as_bytes()[pos] as char;
+            let event = &rest[pos + 1..];
+            if c != '!' {
+                quote = match quote {
+                    None => Some(c),
+                    Some(open) if open == c => None,
+                    open => open,
+                };
+                result.push(c);
+                rest = event;
+                continue;
+            }
+            if quote == Some('\'') {
+                result.push('!');
+                rest = event;
+                continue;
+            }
+
+            if let Some(tail) = event.strip_prefix('!') {
+                let last = self
+                    .entries
+                    .back()
+                    .ok_or_else(|| String::from("!!: event not found"))?;
+                result.push_str(last);
+                rest = tail;
+                continue;
+            }
+
+            let negative = event.starts_with('-');
+            let digits_start = if negative { 1 } else { 0 };
+            let digits_len = event[digits_start..]
+                .bytes()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
+            if digits_len == 0 {
+                // Одиночный `!` оставляем как есть
+                result.push('!');
+                rest = event;
+                continue;
+            }
+
+            let spec = &event[..digits_start + digits_len];
+            let n: usize = event[digits_start..digits_start + digits_len]
+                .parse()
+                .map_err(|_| format!("!{}: event not found", spec))?;
+            let number = if negative {
+                (self.first_number + self.entries.len()).checked_sub(n)
+            } else {
+                Some(n)
+            };
+            let entry = number
+                .and_then(|number| self.get(number))
+                .ok_or_else(|| format!("!{}: event not found", spec))?;
+            result.push_str(entry);
+            rest = &event[digits_start + digits_len..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+// Состояние поиска по истории (Ctrl+R)
+pub struct ReverseSearch {
+    pub query: String,
+    pub index: Option<usize>,
+}
+
+impl ReverseSearch {
+    pub const fn new() -> Self {
+        ReverseSearch {
+            query: String::new(),
+            index: None,
+        }
+    }
+
+    // Ищет заново от самой новой записи (после изменения запроса)
+    pub fn update(&mut self, history: &History) {
+        self.index = if self.query.is_empty() {
+            None
+        } else {
+            history.search(&self.query, history.len())
+        };
+    }
+
+    // Ctrl+R повторно: следующее совпадение среди более старых записей
+    pub fn older(&mut self, history: &History) {
+        if self.query.is_empty() {
+            return;
+        }
+        let before = self.index.unwrap_or(history.len());
+        if let Some(index) = history.search(&self.query, before) {
+            self.index = Some(index);
+        }
+    }
+}
+
+pub fn init() {
+    register(Command {
+        name: "history",
+        aliases: &[],
+        description: "List previously entered commands",
+        usage: "history [-c|--clear] [-n|--count N]",
+        args: ArgSpec {
+            options: &[
+                OptSpec {
+                    name: "clear",
+                    short: Some('c'),
+                    takes_value: false,
+                },
+                OptSpec {
+                    name: "count",
+                    short: Some('n'),
+                    takes_value: true,
+                },
+            ],
+            min: 0,
+            max: 0,
+        },
+        handler: history_action,
+    });
+}
+
+fn history_action(io: &mut Io, args: &Args) -> i32 {
+    if args.flag("clear") {
+        HISTORY.lock().clear();
+        return 0;
+    }
+
+    let history = HISTORY.lock();
+    let count = match args.value("count").map(|count| count.parse::<usize>()) {
+        None => history.len(),
+        Some(Ok(count)) => count,
+        Some(Err(_)) => {
+            drop(history);
+            eprintln!("history: invalid count");
+            return 1;
+        }
+    };
+    let skip = history.len().saturating_sub(count);
+    for (number, entry) in history.iter().skip(skip) {
+        let _ = writeln!(io, "{:5}  {}", number, entry);
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test_case]
+    fn expand_events() {
+        let mut history = History::new();
+        history.push("echo one");
+        history.push("echo two");
+        assert_eq!(history.expand("!!").unwrap(), "echo two");
+        assert_eq!(history.expand("!1 | wc").unwrap(), "echo one | wc");
+        assert_eq!(history.expand("!-2").unwrap(), "echo one");
+        assert_eq!(history.expand("echo hi!").unwrap(), "echo hi!");
+        assert!(history.expand("!9").is_err());
+    }
+
+    #[test_case]
+    fn no_expansion_in_single_quotes() {
+        let mut history = History::new();
+        history.push("ls");
+        assert_eq!(history.expand("echo '!!' !!").unwrap(), "echo '!!' ls");
+        assert_eq!(
+            history.expand("echo \"it's !!\"").unwrap(),
+            "echo \"it's ls\""
+        );
+        assert_eq!(history.expand("echo 'a!1").unwrap(), "echo 'a!1");
+    }
+}
@@ -1,8 +1,15 @@
+use crate::apic;
 use crate::datetime::{CURRENT_TIME, TICKS};
+use crate::gdt::DOUBLE_FAULT_IST_INDEX;
+use crate::log;
+use crate::memory;
+use crate::panic::{fail, Context};
 use crate::pic::{ChainedPics, PIC_1_OFFSET, PIC_2_OFFSET};
+use crate::serial;
 use core::sync::atomic::Ordering;
 use x86_64::instructions::port::Port;
-use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
+use x86_64::registers::control::Cr2;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
 
 static mut IDT: InterruptDescriptorTable = InterruptDescriptorTable::new();
 static PICS: spin::Mutex<ChainedPics> =
@@ -13,8 +20,14 @@ static PICS: spin::Mutex<ChainedPics> =
 pub enum InterruptIndex {
     Timer = PIC_1_OFFSET,
     Keyboard = PIC_1_OFFSET + 1,
+    Serial = PIC_1_OFFSET + 4, // COM1
 }
 
+// IRQ шины ISA, которые обслуживает ядро
+const TIMER_IRQ: u8 = 0;
+const KEYBOARD_IRQ: u8 = 1;
+const SERIAL_IRQ: u8 = 4;
+
 impl InterruptIndex {
     fn as_u8(self) -> u8 {
         self as u8
@@ -25,6 +38,14 @@ impl InterruptIndex {
     }
 }
 
+fn end_of_interrupt(index: InterruptIndex) {
+    if apic::enabled() {
+        apic::end_of_interrupt();
+    } else {
+        unsafe { PICS.lock().notify_end_of_interrupt(index.as_u8()) }
+    }
+}
+
 extern "x86-interrupt" fn pit_interrupt_handler(_stack_frame: InterruptStackFrame) {
     TICKS.fetch_add(1, Ordering::Relaxed);
 
@@ -33,10 +54,7 @@ extern "x86-interrupt" fn pit_interrupt_handler(_stack_frame: InterruptStackFram
         time.update();
     }
 
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
-    }
+    end_of_interrupt(InterruptIndex::Timer);
 }
 
 extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
@@ -45,22 +63,125 @@ extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStac
         let _scancode: u8 = port.read();
 
         // Здесь можно добавить обработку кода клавиши
+    }
+    end_of_interrupt(InterruptIndex::Keyboard);
+}
+
+extern "x86-interrupt" fn serial_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    serial::handle_interrupt();
+    end_of_interrupt(InterruptIndex::Serial);
+}
+
+// Ложное прерывание Local APIC подтверждать не нужно
+extern "x86-interrupt" fn apic_spurious_handler(_stack_frame: InterruptStackFrame) {}
 
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
+// Ложные IRQ7 и IRQ15 от 8259 приходят даже при замаскированных линиях.
+// Для ведомого контроллера ведущий всё же получил сигнал по каскаду.
+extern "x86-interrupt" fn pic_master_spurious_handler(_stack_frame: InterruptStackFrame) {}
+
+extern "x86-interrupt" fn pic_slave_spurious_handler(_stack_frame: InterruptStackFrame) {
+    if !apic::enabled() {
+        unsafe { PICS.lock().notify_end_of_interrupt(PIC_1_OFFSET) }
     }
 }
 
+// Обращение к неотображённой странице. Попадание в сторожевую страницу
+// означает переполнение стека ядра.
+extern "x86-interrupt" fn page_fault_handler(
+    stack_frame: InterruptStackFrame,
+    error_code: PageFaultErrorCode,
+) {
+    let address = Cr2::read().as_u64();
+    fail(
+        if memory::is_stack_guard(address) {
+            "KERNEL STACK OVERFLOW"
+        } else {
+            "PAGE FAULT"
+        },
+        format_args!("address {:#x}, {:?}", address, error_code),
+        Context::Exception(&stack_frame),
+    );
+}
+
+// Выполняется на отдельном стеке (IST): сюда попадает исключение страницы,
+// которое процессор не смог доставить на переполненный стек
+extern "x86-interrupt" fn double_fault_handler(stack_frame: InterruptStackFrame, _: u64) -> ! {
+    let address = Cr2::read().as_u64();
+    fail(
+        if memory::is_stack_guard(address) {
+            "KERNEL STACK OVERFLOW"
+        } else {
+            "DOUBLE FAULT"
+        },
+        format_args!("last page fault address {:#x}", address),
+        Context::Exception(&stack_frame),
+    );
+}
+
 pub fn init_idt() {
     unsafe {
-        IDT[InterruptIndex::Timer.as_usize()].set_handler_fn(pit_interrupt_handler);
-        IDT[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
         let idt = &raw mut IDT;
+        (*idt).page_fault.set_handler_fn(page_fault_handler);
+        (*idt)
+            .double_fault
+            .set_handler_fn(double_fault_handler)
+            .set_stack_index(DOUBLE_FAULT_IST_INDEX);
+        (*idt)[InterruptIndex::Timer.as_usize()].set_handler_fn(pit_interrupt_handler);
+        (*idt)[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
+        (*idt)[InterruptIndex::Serial.as_usize()].set_handler_fn(serial_interrupt_handler);
+        (*idt)[usize::from(PIC_1_OFFSET + 7)].set_handler_fn(pic_master_spurious_handler);
+        (*idt)[usize::from(PIC_2_OFFSET + 7)].set_handler_fn(pic_slave_spurious_handler);
+        (*idt)[usize::from(apic::SPURIOUS_VECTOR)].set_handler_fn(apic_spurious_handler);
         idt.as_ref().expect("IDT is None").load();
-        PICS.lock().initialize();
     }
+    init_controllers();
+}
+
+// 8259 перенастраивается в любом случае, чтобы его ложные прерывания
+// попадали на известные векторы. Дальше прерывания идут через IOAPIC, а при
+// его отсутствии — через 8259.
+fn init_controllers() {
+    let mut pics = PICS.lock();
+    unsafe { pics.initialize() };
+
+    if apic::init() {
+        unsafe { pics.disable() };
+        apic::route_isa_irq(KEYBOARD_IRQ, InterruptIndex::Keyboard.as_u8());
+        apic::route_isa_irq(SERIAL_IRQ, InterruptIndex::Serial.as_u8());
+        if !apic::start_timer(InterruptIndex::Timer.as_u8()) {
+            apic::route_isa_irq(TIMER_IRQ, InterruptIndex::Timer.as_u8());
+        }
+        return;
+    }
+
+    for irq in [TIMER_IRQ, KEYBOARD_IRQ, SERIAL_IRQ] {
+        unsafe { pics.unmask(irq) };
+    }
+    log::info!("Interrupts routed through 8259 PIC");
 }
 
 pub fn enable_interrupts() {
     x86_64::instructions::interrupts::enable();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::datetime::TICKS;
+    use core::sync::atomic::Ordering;
+    use x86_64::instructions::interrupts;
+
+    #[test_case]
+    fn interrupts_are_enabled() {
+        assert!(interrupts::are_enabled());
+    }
+
+    #[test_case]
+    fn timer_ticks() {
+        let start = TICKS.load(Ordering::Relaxed);
+        // Ждём несколько прерываний PIT (1 мс каждое)
+        for _ in 0..5 {
+            interrupts::enable_and_hlt();
+        }
+        assert!(TICKS.load(Ordering::Relaxed) > start);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::commands::{register, Command, Io};
+use crate::eprintln;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use neonforge_core::args::{ArgSpec, Args};
+use neonforge_core::keyboard::{Layout, LAYOUTS};
+
+// Индекс текущей раскладки в LAYOUTS
+static LAYOUT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn layout() -> &'static Layout {
+    &LAYOUTS[LAYOUT.load(Ordering::Relaxed)]
+}
+
+pub fn set_layout(name: &str) -> bool {
+    match LAYOUTS.iter().position(|layout| layout.name == name) {
+        Some(index) => {
+            LAYOUT.store(index, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn init() {
+    register(Command {
+        name: "layout",
+        aliases: &[],
+        description: "Show or change the keyboard layout",
+        usage: "layout [NAME]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: 1,
+        },
+        handler: layout_action,
+    });
+}
+
+fn layout_action(io: &mut Io, args: &Args) -> i32 {
+    let Some(&name) = args.positional.first() else {
+        let current = layout().name;
+        for name in LAYOUTS.iter().map(|layout| layout.name) {
+            let mark = if name == current { "*" } else { " " };
+            let _ = writeln!(io, "{} {}", mark, name);
+        }
+        return 0;
+    };
+
+    if set_layout(name) {
+        0
+    } else {
+        eprintln!("layout: unknown layout: {}", name);
+        1
+    }
+}
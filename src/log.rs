@@ -0,0 +1,232 @@
+use crate::commands::{register, Command, Io};
+use crate::console::Console;
+use crate::constants::{LOG_BUFFER_SIZE, TICKS_PER_SECOND};
+use crate::datetime::TICKS;
+use crate::serial::{self, COM1};
+use crate::theme;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::sync::atomic::Ordering;
+use neonforge_core::args::{ArgSpec, Args, OptSpec};
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+}
+
+impl Level {
+    fn prefix(self) -> &'static str {
+        match self {
+            Level::Error => "error: ",
+            Level::Warn => "warn: ",
+            Level::Info => "",
+        }
+    }
+
+    fn color(self) -> u8 {
+        match self {
+            Level::Error => theme::error(),
+            Level::Warn => 0x0e,
+            Level::Info => theme::text(),
+        }
+    }
+}
+
+// Кольцевой буфер сообщений ядра. Не использует кучу, поэтому писать
+// в журнал можно с самого начала загрузки. Старые строки затираются.
+struct RingBuffer {
+    data: [u8; LOG_BUFFER_SIZE],
+    start: usize,
+    len: usize,
+    wrapped: bool,
+    line_start: bool,
+}
+
+impl RingBuffer {
+    const fn new() -> Self {
+        RingBuffer {
+            data: [0; LOG_BUFFER_SIZE],
+            start: 0,
+            len: 0,
+            wrapped: false,
+            line_start: true,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.len == LOG_BUFFER_SIZE {
+            self.start = (self.start + 1) % LOG_BUFFER_SIZE;
+            self.len -= 1;
+            self.wrapped = true;
+        }
+        self.data[(self.start + self.len) % LOG_BUFFER_SIZE] = byte;
+        self.len += 1;
+    }
+
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        let bytes = (0..self.len).map(|i| self.data[(self.start + i) % LOG_BUFFER_SIZE]);
+        // После перезаписи первая строка может быть обрезана: пропускаем её
+        let skip = if self.wrapped {
+            bytes
+                .clone()
+                .position(|b| b == b'\n')
+                .map_or(self.len, |pos| pos + 1)
+        } else {
+            0
+        };
+        bytes.skip(skip)
+    }
+
+    fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+        self.wrapped = false;
+    }
+}
+
+impl Write for RingBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.push(byte);
+        }
+        Ok(())
+    }
+}
+
+static LOG: Mutex<RingBuffer> = Mutex::new(RingBuffer::new());
+
+// Приёмники журнала: кольцевой буфер, экран и COM1
+struct Sinks<'a> {
+    log: &'a mut RingBuffer,
+    console: Console,
+}
+
+impl Write for Sinks<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.log.write_str(s)?;
+        self.console.write_str(s)?;
+        // В режиме последовательной консоли текст уже продублирован в COM1
+        if serial::console_enabled() {
+            Ok(())
+        } else {
+            COM1.lock().write_str(s)
+        }
+    }
+}
+
+// Добавляет метку времени (секунды с момента загрузки) в начало каждой строки
+struct Stamped<'a> {
+    sinks: Sinks<'a>,
+    line_start: bool,
+}
+
+impl Write for Stamped<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.line_start {
+                let ticks = TICKS.load(Ordering::Relaxed);
+                write!(
+                    self.sinks,
+                    "[{:5}.{:03}] ",
+                    ticks / TICKS_PER_SECOND,
+                    ticks % TICKS_PER_SECOND * 1000 / TICKS_PER_SECOND
+                )?;
+            }
+            self.sinks.write_str(line)?;
+            self.line_start = line.ends_with('\n');
+        }
+        Ok(())
+    }
+}
+
+pub fn _log(level: Option<Level>, args: fmt::Arguments) {
+    // Прерывания отключены, чтобы обработчик не попытался писать
+    // в журнал, пока блокировка уже захвачена
+    without_interrupts(|| {
+        let mut log = LOG.lock();
+        let line_start = log.line_start;
+        let color = level.map_or(theme::text(), Level::color);
+        let mut out = Stamped {
+            sinks: Sinks {
+                log: &mut log,
+                console: Console::with_color(color),
+            },
+            line_start,
+        };
+
+        if let Some(level) = level {
+            // Сообщение с уровнем всегда занимает отдельную строку
+            if !out.line_start {
+                let _ = out.write_str("\n");
+            }
+            let _ = out.write_str(level.prefix());
+            let _ = out.write_fmt(args);
+            let _ = out.write_str("\n");
+        } else {
+            let _ = out.write_fmt(args);
+        }
+
+        log.line_start = out.line_start;
+    });
+}
+
+#[macro_export]
+macro_rules! kprint {
+    ($($arg:tt)*) => ($crate::log::_log(None, format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! kprintln {
+    () => ($crate::kprint!("\n"));
+    ($($arg:tt)*) => ($crate::kprint!("{}\n", format_args!($($arg)*)));
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => ($crate::log::_log(Some($crate::log::Level::Info), format_args!($($arg)*)));
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => ($crate::log::_log(Some($crate::log::Level::Warn), format_args!($($arg)*)));
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => ($crate::log::_log(Some($crate::log::Level::Error), format_args!($($arg)*)));
+}
+
+// `log::info!(...)` и т.д.; внутренние имена не конфликтуют с атрибутом `warn`
+pub(crate) use {log_error as error, log_info as info, log_warn as warn};
+
+pub fn init() {
+    register(Command {
+        name: "dmesg",
+        aliases: &[],
+        description: "Show the kernel log",
+        usage: "dmesg [-c|--clear]",
+        args: ArgSpec {
+            options: &[OptSpec {
+                name: "clear",
+                short: Some('c'),
+                takes_value: false,
+            }],
+            min: 0,
+            max: 0,
+        },
+        handler: dmesg_action,
+    });
+}
+
+fn dmesg_action(io: &mut Io, args: &Args) -> i32 {
+    // Копируем журнал, чтобы не держать блокировку во время вывода
+    let text: Vec<u8> = without_interrupts(|| LOG.lock().bytes().collect());
+    let _ = write!(io, "{}", String::from_utf8_lossy(&text));
+
+    if args.flag("clear") {
+        without_interrupts(|| LOG.lock().clear());
+    }
+    0
+}
@@ -3,6 +3,9 @@
 #![feature(abi_x86_interrupt)]
 #![feature(global_allocator)]
 #![feature(alloc_error_handler)]
+#![feature(custom_test_frameworks)]
+#![test_runner(crate::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
 
 extern crate alloc;
 
@@ -11,48 +14,64 @@ use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use core::panic::PanicInfo;
 use x86_64::instructions::port::Port;
+mod acpi;
+mod allocator;
+mod apic;
+mod ata;
 mod commands;
+mod completion;
+mod console;
 mod constants;
+mod cpu;
 mod datetime;
-mod eng;
+mod editor;
 // mod file_system;
+mod fs;
+mod gdt;
 mod gpio;
+#[cfg(feature = "debug-alloc")]
+mod heap_debug;
+mod history;
 mod interrupts;
+mod keyboard;
+mod log;
+mod memory;
+mod panic;
+mod pci;
 mod pic;
 mod pit;
+mod power;
+mod prompt;
+mod serial;
+mod shell;
+#[cfg(test)]
+mod testing;
+mod theme;
 mod vga;
 
-use core::mem::MaybeUninit;
-use linked_list_allocator::LockedHeap;
+use bootloader::{entry_point, BootInfo};
 
-use crate::eng::SCANCODE_MAP;
+use completion::Completion;
+use console::{BUFFER, COLORS};
 use constants::{
-    COLOR_INFO, COLS, CURRENT_COL, CURRENT_ROW, HEAP_SIZE, MAX_LINES, MSG, PARTITION_OFFSET, ROWS,
+    COLS, CURRENT_COL, CURRENT_ROW, HEAP_INITIAL_SIZE, MAX_INPUT_LEN, MAX_LINES, PARTITION_OFFSET,
+    ROWS, TICKS_PER_SECOND,
 };
 use datetime::{get_date, get_time};
+use editor::LineEditor;
+use history::{ReverseSearch, HISTORY};
 use interrupts::{enable_interrupts, init_idt};
+use neonforge_core::keyboard::{Key, Keyboard};
 use pit::init_pit;
+use prompt::Cell;
 
 use core::ptr::NonNull;
 use embedded_sdmmc::{Controller, Mode, VolumeIdx};
 // use file_system::MyBlockDevice;
 
+use core::fmt::Write;
 use gpio::Gpio;
-use vga::{write_char, write_string};
-
-#[global_allocator]
-static ALLOCATOR: LockedHeap = LockedHeap::empty();
-
-fn init_heap() {
-    static mut HEAP_MEMORY: MaybeUninit<[u8; HEAP_SIZE]> = MaybeUninit::uninit();
-
-    unsafe {
-        let heap_start = HEAP_MEMORY.as_mut_ptr() as *mut u8;
-        ALLOCATOR.lock().init(heap_start, HEAP_SIZE);
-    }
-}
 
 struct MyTimeSource;
 
@@ -78,20 +97,70 @@ static ASCII_LOGO: &[u8] = br#"
                                 |___|    
 "#;
 
-static mut BUFFER: [[u8; COLS]; ROWS] = [[0; COLS]; ROWS];
 static mut CURSOR_POSITION_ROW: usize = 0;
 static mut CURSOR_POSITION_COL: usize = 0;
-static mut INPUT_BUFFER: String = String::new();
+static mut LINE_EDITOR: LineEditor = LineEditor::new();
+static mut KEYBOARD: Keyboard = Keyboard::new();
+static mut PROMPT_ROW: usize = 0; // Строка, с которой начинается приглашение
+static mut INPUT_ROWS: usize = 0; // Сколько строк экрана занимает ввод
+static mut SEARCH: Option<ReverseSearch> = None; // Активный поиск Ctrl+R
+static mut LAST_KEY_TAB: bool = false; // Предыдущей клавишей был Tab
+
+entry_point!(kernel_main);
 
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
-    write_string(0, 0, "Initializing heap...", 0x0F);
-    init_heap();
-    write_string(1, 0, "Heap initialized.", 0x0F);
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    serial::init();
+    log::info!("NeonForge booting");
+    cpu::init();
 
+    memory::init(boot_info);
+
+    if let Err(msg) = allocator::init() {
+        panic!("heap initialization failed: {}", msg);
+    }
+    log::info!("Heap initialized: {} KiB", HEAP_INITIAL_SIZE / 1024);
+
+    acpi::init();
+    pci::init();
+    vga::register_driver();
+
+    ata::init();
+    fs::mount();
+    log::info!("RAM filesystem mounted");
+
+    commands::init();
+    history::init();
+    fs::init();
+    shell::init();
+    keyboard::init();
+    theme::init();
+    log::init();
+    serial::register_commands();
+    cpu::register_commands();
+    acpi::register_commands();
+    apic::register_commands();
+    memory::register_commands();
+    pci::register_commands();
+    #[cfg(feature = "debug-alloc")]
+    heap_debug::register_commands();
+
+    gdt::init();
     init_idt();
     init_pit();
     enable_interrupts();
+    log::info!(
+        "Interrupts enabled, {} timer at {} Hz",
+        if apic::timer_enabled() {
+            "LAPIC"
+        } else {
+            "PIT"
+        },
+        TICKS_PER_SECOND
+    );
+
+    // `cargo test`: тесты выполняются на полностью инициализированном ядре
+    #[cfg(test)]
+    test_main();
 
     // Инициализация блокового устройства
     // write_string(2, 0, "Initializing block device...", 0x0F);
@@ -152,40 +221,41 @@ pub extern "C" fn _start() -> ! {
             screen_width as usize / 2 - 1,
         );
 
-        vga::clear_screen(screen_width, screen_height);
-        CURRENT_COL = print_prompt(CURRENT_ROW, CURRENT_COL);
+        console::clear();
 
-        CURSOR_POSITION_COL = CURRENT_COL;
+        // Настройки и скрипт автозагрузки выводят текст над первым приглашением
+        shell::load_config();
+        shell::autoexec();
+        console::finish_line();
+
+        // Всё, что выделено до первого приглашения, утечкой не считается
+        #[cfg(feature = "debug-alloc")]
+        heap_debug::checkpoint();
+
+        PROMPT_ROW = CURRENT_ROW;
+        redraw_input();
+        vga::print_buffer(&raw mut BUFFER, &raw mut COLORS);
 
         // Отображение курсора на текущей позиции
-        let cursor_row = CURSOR_POSITION_ROW;
-        let cursor_col = CURSOR_POSITION_COL;
-        let vga_buffer = 0xb8000 as *mut u8;
-        *vga_buffer.offset((cursor_row as isize * COLS as isize + cursor_col as isize) * 2) = b'_';
-        *vga_buffer.offset((cursor_row as isize * COLS as isize + cursor_col as isize) * 2 + 1) =
-            0x07;
+        draw_cursor(screen_width);
 
         loop {
             scroll_status();
             date_status();
             time_status();
-            if let Some(key) = get_key() {
+            if let Some(scancode) = get_key() {
+                if let Some(key) = KEYBOARD.decode(scancode, keyboard::layout()) {
+                    print_key(key, screen_width, screen_height);
+                }
+            }
+            // Ввод с последовательной консоли обрабатывается так же, как клавиатура
+            while let Some(key) = serial::read_key() {
                 print_key(key, screen_width, screen_height);
             }
         }
     }
 }
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    loop {}
-}
-
-#[alloc_error_handler]
-fn alloc_error_handler(_layout: core::alloc::Layout) -> ! {
-    loop {}
-}
-
 fn print_centered(msg: &[u8], width: u16, height: u16) {
     let mut lines: [&[u8]; MAX_LINES] = [&[]; MAX_LINES]; // Инициализация массива строк
     let mut line_count = 0;
@@ -246,16 +316,99 @@ fn print_loading_animation(row: usize, col: usize) {
     }
 }
 
-fn print_prompt(row: usize, col: usize) -> usize {
+fn redraw_input() {
     unsafe {
-        let msg = MSG;
-        for (i, &byte) in msg.iter().enumerate() {
-            BUFFER[row][col + i] = byte;
+        if let Some(search) = &SEARCH {
+            let history = HISTORY.lock();
+            let found = search.index.and_then(|i| history.entry(i));
+            let prompt = match found {
+                None if !search.query.is_empty() => {
+                    format!("(failed reverse-i-search)'{}': ", search.query)
+                }
+                _ => format!("(reverse-i-search)'{}': ", search.query),
+            };
+            let line = found.unwrap_or("");
+            let cursor = line.find(search.query.as_str()).unwrap_or(0);
+            let prompt: Vec<Cell> = prompt.bytes().map(|byte| (byte, theme::text())).collect();
+            redraw_line(&prompt, line, cursor);
+        } else {
+            let prompt = prompt::render(&shell::prompt());
+            redraw_line(&prompt, LINE_EDITOR.as_str(), LINE_EDITOR.cursor());
         }
+    }
+}
+
+// Перерисовывает приглашение и строку ввода в BUFFER, перенося длинные строки.
+// Приглашение может быть любой длины и содержать цвета.
+fn redraw_line(prompt: &[Cell], line: &str, cursor: usize) {
+    unsafe {
+        for row in PROMPT_ROW..PROMPT_ROW + INPUT_ROWS {
+            BUFFER[row] = [0; COLS];
+            COLORS[row] = [0; COLS];
+        }
+
+        let rows = (prompt.len() + line.len()) / COLS + 1;
+
+        // Последняя строка экрана занята панелью состояния
+        while PROMPT_ROW + rows > ROWS - 1 && PROMPT_ROW > 0 {
+            console::scroll();
+            PROMPT_ROW -= 1;
+        }
+
+        let text = line.bytes().map(|byte| (byte, theme::text()));
+        for (i, (byte, color)) in prompt.iter().copied().chain(text).enumerate() {
+            BUFFER[PROMPT_ROW + i / COLS][i % COLS] = byte;
+            COLORS[PROMPT_ROW + i / COLS][i % COLS] = color;
+        }
+        INPUT_ROWS = rows;
+
+        let pos = prompt.len() + cursor;
+        CURRENT_ROW = PROMPT_ROW + pos / COLS;
+        CURRENT_COL = pos % COLS;
+        CURSOR_POSITION_ROW = CURRENT_ROW;
+        CURSOR_POSITION_COL = CURRENT_COL;
+    }
+    mirror_line(prompt, line, cursor);
+}
 
-        vga::print_buffer(&raw mut BUFFER);
+// Перерисовывает строку ввода в терминале последовательной консоли:
+// возврат каретки, приглашение без цветов, ввод, очистка хвоста и курсор
+fn mirror_line(prompt: &[Cell], line: &str, cursor: usize) {
+    if !serial::console_enabled() {
+        return;
+    }
+    let mut text = String::from("\r");
+    text.extend(
+        prompt
+            .iter()
+            .filter(|(byte, _)| *byte != 0)
+            .map(|&(byte, _)| byte as char),
+    );
+    text.push_str(line);
+    text.push_str("\x1b[K");
+    if cursor < line.len() {
+        let _ = write!(text, "\x1b[{}D", line.len() - cursor);
+    }
+    serial::mirror(&text);
+}
 
-        return col + msg.len();
+fn draw_cursor(width: u16) {
+    unsafe {
+        let vga_buffer = 0xb8000 as *mut u8;
+        let offset =
+            (CURSOR_POSITION_ROW as isize * width as isize + CURSOR_POSITION_COL as isize) * 2;
+        if BUFFER[CURSOR_POSITION_ROW][CURSOR_POSITION_COL] == 0 {
+            // В режиме замены курсор отображается блоком
+            *vga_buffer.offset(offset) = if LINE_EDITOR.insert_mode() {
+                b'_'
+            } else {
+                0xDB
+            };
+            *vga_buffer.offset(offset + 1) = 0x07;
+        } else {
+            // Курсор над символом: инвертируем цвет
+            *vga_buffer.offset(offset + 1) = 0x70;
+        }
     }
 }
 
@@ -269,9 +422,12 @@ fn get_key() -> Option<u8> {
     let mut port = Port::new(0x60);
     let scancode: u8 = unsafe { port.read() };
 
+    // Backspace, Delete и стрелки влево/вправо повторяются при удержании
+    const REPEAT_SCANCODES: [u8; 4] = [0x0E, 0x53, 0x4B, 0x4D];
+
     static mut LAST_SCANCODE: u8 = 0;
     unsafe {
-        if scancode == 0x0E {
+        if REPEAT_SCANCODES.contains(&scancode) {
             delay(200000);
             Some(scancode)
         } else if scancode != LAST_SCANCODE {
@@ -283,102 +439,176 @@ fn get_key() -> Option<u8> {
     }
 }
 
-fn print_key(key: u8, width: u16, height: u16) {
+fn print_key(key: Key, width: u16, height: u16) {
+    unsafe {
+        if SEARCH.is_some() {
+            search_key(key);
+        } else {
+            edit_key(key);
+        }
+
+        redraw_input();
+
+        // Очищаем экран
+        vga::clear_screen(width, height);
+
+        // Печать буфера на экране
+        vga::print_buffer(&raw mut BUFFER, &raw mut COLORS);
+
+        // Отображение курсора на текущей позиции
+        draw_cursor(width);
+    }
+}
+
+fn edit_key(key: Key) {
     unsafe {
-        if key == 0x0E {
-            // Обработка Backspace
-            if CURRENT_COL > MSG.len() {
-                BUFFER[CURRENT_ROW][CURRENT_COL] = 0;
-                CURRENT_COL -= 1;
-                BUFFER[CURRENT_ROW][CURRENT_COL] = 0;
-                INPUT_BUFFER.pop();
+        match key {
+            Key::Enter => execute_input(),
+            Key::Char(character) => LINE_EDITOR.put(character),
+            Key::Backspace => LINE_EDITOR.backspace(),
+            Key::Delete => LINE_EDITOR.delete(),
+            Key::Insert => LINE_EDITOR.toggle_insert_mode(),
+            Key::Left => LINE_EDITOR.move_left(),
+            Key::Right => LINE_EDITOR.move_right(),
+            Key::Home | Key::Ctrl('a') => LINE_EDITOR.move_home(),
+            Key::End | Key::Ctrl('e') => LINE_EDITOR.move_end(),
+            Key::Ctrl('k') => LINE_EDITOR.kill_to_end(),
+            Key::Ctrl('u') => LINE_EDITOR.kill_to_start(),
+            Key::Ctrl('w') => LINE_EDITOR.kill_word_back(),
+            Key::Up => {
+                if let Some(entry) = HISTORY.lock().previous(LINE_EDITOR.as_str()) {
+                    LINE_EDITOR.set(entry);
+                }
             }
-        } else if let Some(character) = SCANCODE_MAP[key as usize] {
-            if character == '\n' {
-                // Выполнение команды и отображение текущей строки
-                let stat: bool = commands::command_fn(&raw mut BUFFER, CURRENT_ROW, &INPUT_BUFFER);
-                if !stat {
-                    CURRENT_ROW += 2;
+            Key::Down => {
+                if let Some(entry) = HISTORY.lock().next() {
+                    LINE_EDITOR.set(entry);
                 }
+            }
+            Key::Ctrl('r') => SEARCH = Some(ReverseSearch::new()),
+            Key::Tab => complete_input(),
+            Key::Ctrl(_) | Key::Escape => {}
+        }
+        LAST_KEY_TAB = key == Key::Tab;
+    }
+}
 
-                // Очистка буфера после выполнения команды
-                INPUT_BUFFER.clear();
+fn complete_input() {
+    unsafe {
+        match completion::complete(LINE_EDITOR.as_str(), LINE_EDITOR.cursor(), LAST_KEY_TAB) {
+            Completion::None => {}
+            Completion::Insert { start, text } => LINE_EDITOR.replace_before_cursor(start, &text),
+            Completion::List(names) => {
+                // Список вариантов печатается под вводом, затем приглашение повторяется
+                console::start_below(PROMPT_ROW + INPUT_ROWS - 1);
+                for line in commands::columns(&names) {
+                    println!("{}", line);
+                }
+                start_new_prompt();
+            }
+        }
+    }
+}
 
-                CURRENT_COL = 0;
-                if CURRENT_ROW >= 24 {
-                    scroll();
-                    CURRENT_ROW -= 1;
-                    CURRENT_COL = 0;
+fn search_key(key: Key) {
+    unsafe {
+        let Some(search) = &mut SEARCH else {
+            return;
+        };
+        let history = HISTORY.lock();
+        match key {
+            Key::Char(character) => {
+                if search.query.len() < MAX_INPUT_LEN {
+                    search.query.push(character);
                 }
-                // Печать приглашения
-                CURRENT_COL = print_prompt(CURRENT_ROW, CURRENT_COL);
-            } else {
-                if CURRENT_COL < COLS {
-                    if CURRENT_COL > 78 {
-                        CURRENT_COL = 0;
-                        CURRENT_ROW += 1;
-                    }
-                    BUFFER[CURRENT_ROW][CURRENT_COL] = character as u8;
-                    INPUT_BUFFER.push(character);
-                    CURRENT_COL += 1;
+                search.update(&history);
+            }
+            Key::Backspace => {
+                search.query.pop();
+                search.update(&history);
+            }
+            Key::Ctrl('r') => search.older(&history),
+            // Отмена поиска: строка ввода остаётся прежней
+            Key::Ctrl('g') | Key::Escape => SEARCH = None,
+            _ => {
+                // Любая другая клавиша принимает найденную команду
+                if let Some(entry) = search.index.and_then(|i| history.entry(i)) {
+                    LINE_EDITOR.set(entry);
                 }
+                SEARCH = None;
+                drop(history);
+                edit_key(key);
             }
         }
+    }
+}
 
-        // Обновление текущей позиции курсора
-        CURSOR_POSITION_ROW = CURRENT_ROW;
-        CURSOR_POSITION_COL = CURRENT_COL;
+fn execute_input() {
+    unsafe {
+        // Вывод команды начинается под последней строкой ввода
+        let row = PROMPT_ROW + INPUT_ROWS - 1;
+
+        // Подстановка `!!`/`!n` и сохранение команды в истории
+        let line = {
+            let mut history = HISTORY.lock();
+            history.reset_browse();
+            match history.expand(LINE_EDITOR.as_str()) {
+                Ok(line) => {
+                    history.push(&line);
+                    Some(line)
+                }
+                Err(msg) => {
+                    console::start_below(row);
+                    eprintln!("{}", msg);
+                    None
+                }
+            }
+        };
 
-        // Очищаем экран
-        vga::clear_screen(width, height);
+        if let Some(line) = &line {
+            commands::command_fn(row, line);
+        }
 
-        // Печать буфера на экране
-        vga::print_buffer(&raw mut BUFFER);
+        // Очистка буфера после выполнения команды
+        LINE_EDITOR.clear();
 
-        // Отображение курсора на текущей позиции
-        let cursor_row = CURSOR_POSITION_ROW;
-        let cursor_col = CURSOR_POSITION_COL;
-        let vga_buffer = 0xb8000 as *mut u8;
-        *vga_buffer.offset((cursor_row as isize * width as isize + cursor_col as isize) * 2) = b'_';
-        *vga_buffer.offset((cursor_row as isize * width as isize + cursor_col as isize) * 2 + 1) =
-            0x07;
+        start_new_prompt();
     }
 }
 
-fn scroll() {
+// Переносит приглашение на CURRENT_ROW; оно печатается при следующей перерисовке
+fn start_new_prompt() {
     unsafe {
-        for i in 0..24 {
-            BUFFER[i] = BUFFER[i + 1];
+        while CURRENT_ROW >= ROWS - 1 {
+            console::scroll();
+            CURRENT_ROW -= 1;
         }
-        BUFFER[24] = [0; COLS];
+        CURRENT_COL = 0;
+
+        PROMPT_ROW = CURRENT_ROW;
+        INPUT_ROWS = 0;
     }
 }
 
 fn scroll_status() {
     unsafe {
         if CURRENT_ROW == 24 {
-            scroll();
-            CURRENT_ROW -= 1;
-            CURRENT_COL = 0;
-            CURRENT_COL = print_prompt(CURRENT_ROW, CURRENT_COL);
+            console::scroll();
+            PROMPT_ROW = PROMPT_ROW.saturating_sub(1);
+            redraw_input();
+            vga::print_buffer(&raw mut BUFFER, &raw mut COLORS);
         }
     }
 }
 
 fn time_status() {
     let time = get_time();
-    let time_str = format!("{:02}:{:02}", time.0, time.1);
-
-    for (i, byte) in time_str.bytes().enumerate() {
-        write_char(24, i + 74, byte, COLOR_INFO); // Печатает на строке row + 1
-    }
+    let mut writer = vga::Writer::new(24, 74, theme::info());
+    let _ = write!(writer, "{:02}:{:02}", time.0, time.1);
 }
 
 fn date_status() {
     let date = get_date();
-    let date_str = format!("{:02}.{:02}.{:04}", date.0, date.1, date.2);
-
-    for (i, byte) in date_str.bytes().enumerate() {
-        write_char(24, i + 62, byte, COLOR_INFO); // Печатает на строке row + 1
-    }
+    let mut writer = vga::Writer::new(24, 62, theme::info());
+    let _ = write!(writer, "{:02}.{:02}.{:04}", date.0, date.1, date.2);
 }
@@ -0,0 +1,375 @@
+use crate::allocator;
+use crate::commands::{register, Command, Io};
+use crate::constants::{
+    FRAME_SIZE, KERNEL_STACK_ADDRESS, MMIO_SIZE, MMIO_START, PHYSICAL_MEMORY_OFFSET, STACKS_SIZE,
+    STACKS_START, STACK_SIZE,
+};
+use crate::log;
+use alloc::string::{String, ToString};
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use bootloader::BootInfo;
+use core::fmt::Write;
+use core::iter;
+use core::{ptr, slice};
+use neonforge_core::args::{ArgSpec, Args};
+use neonforge_core::frames::{words_for, FrameBitmap, FrameStats};
+use neonforge_core::slab::BLOCK_SIZES;
+use spin::Mutex;
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::{
+    FrameAllocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags, PhysFrame, Size4KiB,
+};
+use x86_64::{PhysAddr, VirtAddr};
+
+// Загрузчик отображает всю физическую память начиная с PHYSICAL_MEMORY_OFFSET
+// (см. [package.metadata.bootloader] в Cargo.toml)
+pub fn phys_to_virt(addr: u64) -> *mut u8 {
+    (addr + PHYSICAL_MEMORY_OFFSET) as *mut u8
+}
+
+// Читает значение по физическому адресу; выравнивание не требуется
+pub unsafe fn read_phys<T: Copy>(addr: u64) -> T {
+    ptr::read_unaligned(phys_to_virt(addr) as *const T)
+}
+
+// Распределитель физических кадров. Карта хранится в начале первого
+// подходящего свободного региона и сама занимает его кадры.
+static FRAMES: Mutex<Option<FrameBitmap<'static>>> = Mutex::new(None);
+
+fn usable(memory_map: &MemoryMap) -> impl Iterator<Item = (usize, usize)> + '_ {
+    memory_map
+        .iter()
+        .filter(|region| region.region_type == MemoryRegionType::Usable)
+        .map(|region| {
+            (
+                region.range.start_frame_number as usize,
+                region.range.end_frame_number as usize,
+            )
+        })
+}
+
+pub fn init(boot_info: &'static BootInfo) {
+    assert_eq!(
+        boot_info.physical_memory_offset, PHYSICAL_MEMORY_OFFSET,
+        "physical memory mapped at an unexpected offset"
+    );
+    let memory_map = &boot_info.memory_map;
+
+    let frames = usable(memory_map).map(|(_, end)| end).max().unwrap_or(0);
+    let words = words_for(frames);
+    let bitmap_frames = (words * 8).div_ceil(FRAME_SIZE as usize);
+    let (bitmap_start, _) = usable(memory_map)
+        .find(|(start, end)| end - start >= bitmap_frames)
+        .expect("no usable memory for the frame bitmap");
+
+    let bits = unsafe {
+        slice::from_raw_parts_mut(
+            phys_to_virt(bitmap_start as u64 * FRAME_SIZE) as *mut u64,
+            words,
+        )
+    };
+    let mut bitmap = FrameBitmap::new(bits, frames);
+    for (start, end) in usable(memory_map) {
+        bitmap.add_free(start, end);
+    }
+    bitmap.reserve(bitmap_start, bitmap_start + bitmap_frames);
+
+    let stats = bitmap.stats();
+    *FRAMES.lock() = Some(bitmap);
+
+    // Активная таблица страниц верхнего уровня, созданная загрузчиком
+    let (level_4, _) = Cr3::read();
+    let level_4 =
+        unsafe { &mut *(phys_to_virt(level_4.start_address().as_u64()) as *mut PageTable) };
+    *MAPPER.lock() =
+        Some(unsafe { OffsetPageTable::new(level_4, VirtAddr::new(PHYSICAL_MEMORY_OFFSET)) });
+    log::info!(
+        "Physical memory: {} KiB usable, {} of {} frames free",
+        stats.total * FRAME_SIZE as usize / 1024,
+        stats.free,
+        stats.total
+    );
+}
+
+pub fn alloc_frame() -> Option<PhysFrame> {
+    let frame = FRAMES.lock().as_mut()?.alloc()?;
+    Some(PhysFrame::containing_address(PhysAddr::new(
+        frame as u64 * FRAME_SIZE,
+    )))
+}
+
+pub fn free_frame(frame: PhysFrame) {
+    let number = (frame.start_address().as_u64() / FRAME_SIZE) as usize;
+    let freed = FRAMES
+        .lock()
+        .as_mut()
+        .is_some_and(|bitmap| bitmap.free(number));
+    assert!(
+        freed,
+        "freeing frame {:#x} that is not allocated",
+        frame.start_address()
+    );
+}
+
+pub fn frame_stats() -> FrameStats {
+    match FRAMES.lock().as_ref() {
+        Some(bitmap) => bitmap.stats(),
+        None => FrameStats {
+            total: 0,
+            used: 0,
+            free: 0,
+        },
+    }
+}
+
+// Кадры для новых таблиц страниц берутся из общего распределителя
+struct Frames;
+
+unsafe impl FrameAllocator<Size4KiB> for Frames {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        alloc_frame()
+    }
+}
+
+static MAPPER: Mutex<Option<OffsetPageTable<'static>>> = Mutex::new(None);
+
+// Следующий свободный адрес окна MMIO; окна не освобождаются
+static MMIO_NEXT: Mutex<u64> = Mutex::new(MMIO_START);
+
+fn pages(start: u64, size: usize) -> impl Iterator<Item = Page> {
+    let first = Page::<Size4KiB>::containing_address(VirtAddr::new(start));
+    let last = Page::containing_address(VirtAddr::new(start + size as u64 - 1));
+    Page::range_inclusive(first, last)
+}
+
+// Отображает страницы [start, start + size) на новые кадры (если задан
+// `phys`, то на кадры подряд начиная с этого адреса). При ошибке уже отображённые
+// страницы снимаются, а выделенные под них кадры возвращаются.
+fn map(
+    start: u64,
+    size: usize,
+    phys: Option<u64>,
+    flags: PageTableFlags,
+) -> Result<(), &'static str> {
+    let mut mapper = MAPPER.lock();
+    let mapper = mapper.as_mut().ok_or("paging is not initialized")?;
+    for (i, page) in pages(start, size).enumerate() {
+        let result = match phys {
+            Some(phys) => Ok(PhysFrame::containing_address(PhysAddr::new(
+                phys + i as u64 * FRAME_SIZE,
+            ))),
+            None => alloc_frame().ok_or("out of physical memory"),
+        }
+        .and_then(|frame| {
+            let mapped = unsafe { mapper.map_to(page, frame, flags, &mut Frames) };
+            mapped.map(|flush| flush.flush()).map_err(|_| {
+                if phys.is_none() {
+                    free_frame(frame);
+                }
+                "page is already mapped or out of memory for page tables"
+            })
+        });
+        if let Err(msg) = result {
+            for page in pages(start, size).take(i) {
+                if let Ok((frame, flush)) = mapper.unmap(page) {
+                    flush.flush();
+                    if phys.is_none() {
+                        free_frame(frame);
+                    }
+                }
+            }
+            return Err(msg);
+        }
+    }
+    Ok(())
+}
+
+// Отображает обычную память ядра (куча, стеки)
+pub fn map_pages(start: u64, size: usize) -> Result<(), &'static str> {
+    map(
+        start,
+        size,
+        None,
+        PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+    )
+}
+
+// Отображает регистры устройства (HPET, LAPIC, IOAPIC, кадровый буфер) в окно
+// MMIO без кэширования и возвращает виртуальный адрес `phys`
+pub fn map_mmio(phys: u64, size: usize) -> Result<*mut u8, &'static str> {
+    let offset = phys % FRAME_SIZE;
+    let size = (offset + size as u64).div_ceil(FRAME_SIZE) * FRAME_SIZE;
+    let virt = {
+        let mut next = MMIO_NEXT.lock();
+        if *next + size > MMIO_START + MMIO_SIZE {
+            return Err("MMIO window is full");
+        }
+        let virt = *next;
+        *next += size;
+        virt
+    };
+    let flags = PageTableFlags::PRESENT
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::NO_CACHE
+        | PageTableFlags::WRITE_THROUGH;
+    map(virt, size as usize, Some(phys - offset), flags)?;
+    Ok((virt + offset) as *mut u8)
+}
+
+// Стеки ядра выделяются подряд, каждому предшествует неотображённая
+// сторожевая страница: переполнение вызывает исключение страницы
+static STACKS_NEXT: Mutex<u64> = Mutex::new(STACKS_START);
+
+// Возвращает вершину нового стека размером STACK_SIZE
+pub fn alloc_stack() -> Result<VirtAddr, &'static str> {
+    let bottom = {
+        let mut next = STACKS_NEXT.lock();
+        if *next + FRAME_SIZE + STACK_SIZE > STACKS_START + STACKS_SIZE {
+            return Err("no room for another kernel stack");
+        }
+        let bottom = *next + FRAME_SIZE;
+        *next = bottom + STACK_SIZE;
+        bottom
+    };
+    map_pages(bottom, STACK_SIZE as usize)?;
+    Ok(VirtAddr::new(bottom + STACK_SIZE))
+}
+
+// Попадает ли адрес в сторожевую страницу стека: загрузчик оставляет её
+// под основным стеком, остальные стеки выделяет `alloc_stack`.
+// Вызывается из обработчиков исключений, поэтому без блокировок.
+pub fn is_stack_guard(addr: u64) -> bool {
+    let page = addr - addr % FRAME_SIZE;
+    let slot = FRAME_SIZE + STACK_SIZE;
+    page == KERNEL_STACK_ADDRESS
+        || ((STACKS_START..STACKS_START + STACKS_SIZE).contains(&page)
+            && (page - STACKS_START).is_multiple_of(slot))
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "mem",
+        aliases: &["free"],
+        description: "Show physical memory and heap usage",
+        usage: "mem",
+        args: ArgSpec::NONE,
+        handler: mem_action,
+    });
+}
+
+fn mem_action(io: &mut Io, _args: &Args) -> i32 {
+    let stats = frame_stats();
+    let kib = |frames: usize| frames * FRAME_SIZE as usize / 1024;
+    let _ = writeln!(io, "{:8} {:>10} {:>10} {:>10}", "", "total", "used", "free");
+    let _ = writeln!(
+        io,
+        "{:8} {:>10} {:>10} {:>10}",
+        "frames", stats.total, stats.used, stats.free
+    );
+    let _ = writeln!(
+        io,
+        "{:8} {:>10} {:>10} {:>10}",
+        "KiB",
+        kib(stats.total),
+        kib(stats.used),
+        kib(stats.free)
+    );
+    // Статистика копируется: вывод может идти в строку конвейера, а значит в кучу
+    let heap = allocator::stats();
+    let _ = writeln!(
+        io,
+        "{:8} {:>10} {:>10} {:>10}",
+        "heap KiB",
+        heap.size / 1024,
+        heap.used / 1024,
+        (heap.size - heap.used) / 1024
+    );
+    let _ = writeln!(io, "heap peak {} KiB", heap.peak / 1024);
+    let _ = writeln!(io);
+    let _ = writeln!(
+        io,
+        "{:>8} {:>8} {:>8} {:>10} {:>10}",
+        "class", "live", "peak", "allocs", "frees"
+    );
+    let classes = BLOCK_SIZES.iter().map(|size| size.to_string());
+    for (class, stats) in classes
+        .chain(iter::once(String::from("large")))
+        .zip(heap.classes.iter().chain(iter::once(&heap.large)))
+    {
+        let _ = writeln!(
+            io,
+            "{:>8} {:>8} {:>8} {:>10} {:>10}",
+            class,
+            stats.live(),
+            stats.peak,
+            stats.allocs,
+            stats.frees
+        );
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        alloc_frame, alloc_stack, frame_stats, free_frame, is_stack_guard, map_mmio, phys_to_virt,
+        MAPPER,
+    };
+    use crate::constants::STACK_SIZE;
+    use x86_64::structures::paging::mapper::Translate;
+    use x86_64::VirtAddr;
+
+    fn translate(addr: u64) -> Option<u64> {
+        let mapper = MAPPER.lock();
+        let phys = mapper.as_ref()?.translate_addr(VirtAddr::new(addr))?;
+        Some(phys.as_u64())
+    }
+
+    #[test_case]
+    fn frames_are_usable_and_returned() {
+        let before = frame_stats();
+        assert!(before.total > 0);
+        let a = alloc_frame().unwrap();
+        let b = alloc_frame().unwrap();
+        assert_ne!(a, b);
+        assert_eq!(frame_stats().used, before.used + 2);
+        // Кадр доступен через отображение физической памяти
+        let page = phys_to_virt(a.start_address().as_u64()) as *mut u64;
+        unsafe {
+            page.write_volatile(0x1234_5678);
+            assert_eq!(page.read_volatile(), 0x1234_5678);
+        }
+        free_frame(a);
+        free_frame(b);
+        assert_eq!(frame_stats(), before);
+    }
+
+    // Окно MMIO указывает на ту же физическую память, смещение внутри страницы сохраняется
+    #[test_case]
+    fn mmio_window_maps_physical_memory() {
+        let frame = alloc_frame().unwrap();
+        let phys = frame.start_address().as_u64();
+        let mmio = map_mmio(phys + 8, 8).unwrap() as *mut u64;
+        assert_eq!(mmio as u64 % 4096, 8);
+        unsafe {
+            mmio.write_volatile(0xFEED_BEEF);
+            assert_eq!(
+                (phys_to_virt(phys + 8) as *const u64).read_volatile(),
+                0xFEED_BEEF
+            );
+        }
+        free_frame(frame);
+    }
+
+    // Под каждым стеком остаётся неотображённая сторожевая страница
+    #[test_case]
+    fn stacks_have_guard_pages() {
+        let top = alloc_stack().unwrap().as_u64();
+        let bottom = top - STACK_SIZE;
+        assert!(translate(top - 8).is_some());
+        assert!(translate(bottom).is_some());
+        assert!(translate(bottom - 1).is_none());
+        assert!(is_stack_guard(bottom - 1));
+        assert!(!is_stack_guard(bottom));
+    }
+}
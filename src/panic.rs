@@ -0,0 +1,334 @@
+use crate::constants::{COLOR_PANIC, COLS, MAX_BACKTRACE_DEPTH, ROWS};
+use crate::serial::COM1;
+use crate::vga;
+use core::alloc::Layout;
+use core::arch::asm;
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+use x86_64::instructions::{hlt, interrupts};
+use x86_64::registers::control::{Cr0, Cr2, Cr3, Cr4};
+use x86_64::registers::rflags;
+use x86_64::structures::idt::InterruptStackFrame;
+
+// Кадр больше этого размера считаем повреждённой цепочкой RBP
+const MAX_FRAME_SIZE: u64 = 1024 * 1024;
+
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+// Вывод экрана паники: весь экран VGA и COM1. Не использует кучу и блокировки,
+// которые мог держать упавший код.
+struct PanicWriter {
+    row: usize,
+    col: usize,
+}
+
+impl PanicWriter {
+    fn new() -> Self {
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                vga::write_char(row, col, b' ', COLOR_PANIC);
+            }
+        }
+        // Паника могла произойти во время записи в порт
+        unsafe { COM1.force_unlock() };
+        PanicWriter { row: 0, col: 0 }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        {
+            let mut port = COM1.lock();
+            if byte == b'\n' {
+                port.write_byte(b'\r');
+            }
+            port.write_byte(byte);
+        }
+
+        if byte == b'\n' {
+            self.row += 1;
+            self.col = 0;
+            return;
+        }
+        if self.col >= COLS {
+            self.row += 1;
+            self.col = 0;
+        }
+        // Всё, что не поместилось на экран, остаётся только в COM1
+        if self.row < ROWS {
+            let byte = if byte.is_ascii() { byte } else { b'?' };
+            vga::write_char(self.row, self.col, byte, COLOR_PANIC);
+        }
+        self.col += 1;
+    }
+}
+
+impl Write for PanicWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+const GENERAL_NAMES: [&str; 14] = [
+    "RAX", "RBX", "RCX", "RDX", "RSI", "RDI", "R8 ", "R9 ", "R10", "R11", "R12", "R13", "R14",
+    "R15",
+];
+
+// Регистры общего назначения (кроме RSP и RBP) при входе в обработчик
+// паники. Снимаются первым делом, пока их не испортил код вывода.
+#[derive(Clone, Copy)]
+pub struct GeneralRegisters([u64; 14]);
+
+impl GeneralRegisters {
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let (rax, rbx, rcx, rdx, rsi, rdi): (u64, u64, u64, u64, u64, u64);
+        let (r8, r9, r10, r11, r12, r13, r14, r15): (u64, u64, u64, u64, u64, u64, u64, u64);
+        unsafe {
+            // Пустая вставка: выходные операнды получают значения регистров как есть
+            asm!(
+                "",
+                out("rax") rax,
+                out("rcx") rcx,
+                out("rdx") rdx,
+                out("rsi") rsi,
+                out("rdi") rdi,
+                out("r8") r8,
+                out("r9") r9,
+                out("r10") r10,
+                out("r11") r11,
+                out("r12") r12,
+                out("r13") r13,
+                out("r14") r14,
+                out("r15") r15,
+                options(nomem, nostack, preserves_flags)
+            );
+            // RBX зарезервирован LLVM, его нельзя назвать операндом
+            asm!("mov {}, rbx", out(reg) rbx, options(nomem, nostack, preserves_flags));
+        }
+        GeneralRegisters([
+            rax, rbx, rcx, rdx, rsi, rdi, r8, r9, r10, r11, r12, r13, r14, r15,
+        ])
+    }
+}
+
+// Что известно о состоянии процессора в момент сбоя
+pub enum Context<'a> {
+    Handler(GeneralRegisters),          // Паника или ошибка выделения памяти
+    Exception(&'a InterruptStackFrame), // Кадр, сохранённый процессором
+}
+
+// Регистры на момент сбоя
+struct Registers<'a> {
+    context: Context<'a>,
+    rsp: u64,
+    rbp: u64,
+    rflags: u64,
+    cr0: u64,
+    cr2: u64,
+    cr3: u64,
+    cr4: u64,
+}
+
+impl<'a> Registers<'a> {
+    #[inline(always)]
+    fn capture(context: Context<'a>) -> Self {
+        let (rsp, rbp): (u64, u64);
+        unsafe {
+            asm!("mov {}, rsp", out(reg) rsp, options(nomem, nostack, preserves_flags));
+            asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags));
+        }
+        Registers {
+            context,
+            rsp,
+            rbp,
+            rflags: rflags::read_raw(),
+            cr0: Cr0::read_raw(),
+            cr2: Cr2::read().as_u64(),
+            cr3: Cr3::read().0.start_address().as_u64(),
+            cr4: Cr4::read_raw(),
+        }
+    }
+}
+
+impl fmt::Display for Registers<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.context {
+            Context::Handler(general) => {
+                for (names, values) in GENERAL_NAMES.chunks(3).zip(general.0.chunks(3)) {
+                    for (i, (name, value)) in names.iter().zip(values).enumerate() {
+                        let separator = if i == 0 { "" } else { " " };
+                        write!(f, "{}{}={:016x}", separator, name, value)?;
+                    }
+                    writeln!(f)?;
+                }
+                writeln!(
+                    f,
+                    "RSP={:016x} RBP={:016x} RFLAGS={:016x}",
+                    self.rsp, self.rbp, self.rflags
+                )?;
+            }
+            Context::Exception(frame) => {
+                writeln!(
+                    f,
+                    "RIP={:016x} CS={:04x} RFLAGS={:016x}",
+                    frame.instruction_pointer.as_u64(),
+                    frame.code_segment,
+                    frame.cpu_flags
+                )?;
+                writeln!(
+                    f,
+                    "RSP={:016x} SS={:04x}",
+                    frame.stack_pointer.as_u64(),
+                    frame.stack_segment
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "CR0={:016x} CR2={:016x} CR3={:016x} CR4={:016x}",
+            self.cr0, self.cr2, self.cr3, self.cr4
+        )
+    }
+}
+
+// Обход стека по цепочке RBP (ядро собирается с -C force-frame-pointers=yes):
+// [rbp] — RBP вызывающей функции, [rbp + 8] — адрес возврата.
+pub struct StackFrames {
+    rbp: u64,
+}
+
+impl StackFrames {
+    pub fn new(rbp: u64) -> Self {
+        StackFrames { rbp }
+    }
+
+    // Начиная с кадра вызывающей функции
+    #[cfg(feature = "debug-alloc")]
+    #[inline(always)]
+    pub fn current() -> Self {
+        let rbp: u64;
+        unsafe { asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags)) };
+        StackFrames { rbp }
+    }
+}
+
+impl Iterator for StackFrames {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        // Кадры лежат выше по стеку; всё остальное — признак конца цепочки
+        if self.rbp == 0 || !self.rbp.is_multiple_of(8) {
+            return None;
+        }
+        let (next, return_address) = unsafe {
+            let frame = self.rbp as *const u64;
+            (*frame, *frame.add(1))
+        };
+        if return_address == 0 {
+            return None;
+        }
+        self.rbp = if next <= self.rbp || next - self.rbp > MAX_FRAME_SIZE {
+            0
+        } else {
+            next
+        };
+        Some(return_address)
+    }
+}
+
+fn backtrace(out: &mut impl Write, rbp: u64) {
+    let _ = writeln!(out, "Backtrace:");
+    let mut frames = StackFrames::new(rbp);
+    for (depth, return_address) in frames.by_ref().take(MAX_BACKTRACE_DEPTH).enumerate() {
+        let _ = writeln!(out, "  #{:<2} {:#018x}", depth, return_address);
+    }
+    if frames.next().is_some() {
+        let _ = writeln!(out, "  ...");
+    }
+}
+
+// Общий путь для паники, ошибки выделения памяти и исключений процессора
+pub fn fail(title: &str, details: fmt::Arguments, context: Context) -> ! {
+    let registers = Registers::capture(context);
+    interrupts::disable();
+
+    // Повторная паника при выводе: просто останавливаемся
+    if PANICKING.swap(true, Ordering::SeqCst) {
+        halt();
+    }
+
+    let mut out = PanicWriter::new();
+    // Завершает строку теста, начатую в testing::Testable::run. Только после
+    // PanicWriter::new: до force_unlock порт COM1 мог быть заблокирован.
+    #[cfg(test)]
+    crate::serial_println!("[failed]\n");
+    let _ = writeln!(out, "*** {} ***", title);
+    let _ = writeln!(out, "{}", details);
+    let _ = writeln!(out);
+    let _ = write!(out, "{}", registers);
+    let _ = writeln!(out);
+    backtrace(&mut out, registers.rbp);
+    let _ = writeln!(out);
+    let _ = write!(out, "System halted.");
+
+    #[cfg(test)]
+    crate::testing::exit_qemu(crate::testing::QemuExitCode::Failed);
+    #[cfg(not(test))]
+    halt();
+}
+
+fn halt() -> ! {
+    loop {
+        hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let context = Context::Handler(GeneralRegisters::capture());
+    match info.location() {
+        Some(location) => fail(
+            "KERNEL PANIC",
+            format_args!(
+                "{}\nat {}:{}:{}",
+                info.message(),
+                location.file(),
+                location.line(),
+                location.column()
+            ),
+            context,
+        ),
+        None => fail("KERNEL PANIC", format_args!("{}", info.message()), context),
+    }
+}
+
+#[alloc_error_handler]
+fn alloc_error_handler(layout: Layout) -> ! {
+    let context = Context::Handler(GeneralRegisters::capture());
+    // Куча могла остаться заблокированной, поэтому статистика необязательна
+    match crate::allocator::ALLOCATOR.try_lock() {
+        Some(heap) => fail(
+            "OUT OF MEMORY",
+            format_args!(
+                "allocation of {} bytes (align {}) failed\nheap: {} bytes used, {} bytes free",
+                layout.size(),
+                layout.align(),
+                heap.used(),
+                heap.free()
+            ),
+            context,
+        ),
+        None => fail(
+            "OUT OF MEMORY",
+            format_args!(
+                "allocation of {} bytes (align {}) failed",
+                layout.size(),
+                layout.align()
+            ),
+            context,
+        ),
+    }
+}
@@ -0,0 +1,564 @@
+use crate::acpi;
+use crate::commands::{register, Command, Io};
+use crate::log;
+use crate::memory;
+use crate::vga;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use neonforge_core::args::{ArgSpec, Args, OptSpec};
+use neonforge_core::pci::{
+    bar_is_64bit, bar_is_io, capability_name, class_name, decode_bar, vendor_name, Bar,
+};
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+
+// Механизм доступа №1 через порты ввода-вывода
+const CONFIG_ADDRESS: u16 = 0xCF8;
+const CONFIG_DATA: u16 = 0xCFC;
+
+// Смещения в заголовке конфигурационного пространства
+const VENDOR_ID: u16 = 0x00;
+const COMMAND: u16 = 0x04;
+const CLASS: u16 = 0x08; // Ревизия, prog-if, подкласс, класс
+const HEADER_TYPE: u16 = 0x0E;
+const BAR0: u16 = 0x10;
+const BUS_NUMBERS: u16 = 0x18; // У моста: первичная, вторичная, подчинённая шины
+const CAPABILITIES: u16 = 0x34;
+const INTERRUPT: u16 = 0x3C; // Линия и вывод прерывания
+
+const STATUS_CAPABILITIES: u32 = 1 << 20; // Бит 4 регистра состояния
+const COMMAND_DECODE: u32 = 0x3; // Декодирование портов и памяти
+const MAX_CAPABILITIES: usize = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+    }
+}
+
+// ECAM: конфигурационное пространство, отображённое в память (PCI Express).
+// Окна шин отображаются при первом обращении; таблица окон лежит в куче.
+struct Ecam {
+    base: u64,
+    start_bus: u8,
+    end_bus: u8,
+    buses: [usize; 256],
+}
+
+enum Access {
+    Ports,
+    Ecam(Box<Ecam>),
+}
+
+static CONFIG: Mutex<Access> = Mutex::new(Access::Ports);
+
+impl Access {
+    fn ecam(&mut self, addr: Address, offset: u16) -> Option<*mut u32> {
+        let Access::Ecam(ecam) = self else {
+            return None;
+        };
+        if addr.bus < ecam.start_bus || addr.bus > ecam.end_bus {
+            return None;
+        }
+        let window = &mut ecam.buses[addr.bus as usize];
+        if *window == 0 {
+            let phys = ecam.base + (((addr.bus - ecam.start_bus) as u64) << 20);
+            *window = memory::map_mmio(phys, 1 << 20).ok()? as usize;
+        }
+        let offset =
+            (addr.device as usize) << 15 | (addr.function as usize) << 12 | offset as usize;
+        Some((*window + offset) as *mut u32)
+    }
+
+    fn select(addr: Address, offset: u16) {
+        let address = 0x8000_0000
+            | (addr.bus as u32) << 16
+            | (addr.device as u32) << 11
+            | (addr.function as u32) << 8
+            | (offset as u32 & 0xFC);
+        unsafe { Port::new(CONFIG_ADDRESS).write(address) };
+    }
+
+    fn read(&mut self, addr: Address, offset: u16) -> u32 {
+        if let Some(ptr) = self.ecam(addr, offset) {
+            return unsafe { ptr.read_volatile() };
+        }
+        Self::select(addr, offset);
+        unsafe { Port::new(CONFIG_DATA).read() }
+    }
+
+    fn write(&mut self, addr: Address, offset: u16, value: u32) {
+        if let Some(ptr) = self.ecam(addr, offset) {
+            return unsafe { ptr.write_volatile(value) };
+        }
+        Self::select(addr, offset);
+        unsafe { Port::new(CONFIG_DATA).write(value) }
+    }
+}
+
+// Чтение и запись выровненного 32-битного слова конфигурационного пространства
+pub fn read_config(addr: Address, offset: u16) -> u32 {
+    CONFIG.lock().read(addr, offset & !0x3)
+}
+
+pub fn write_config(addr: Address, offset: u16, value: u32) {
+    CONFIG.lock().write(addr, offset & !0x3, value)
+}
+
+fn read_u8(addr: Address, offset: u16) -> u8 {
+    (read_config(addr, offset) >> ((offset & 0x3) * 8)) as u8
+}
+
+fn read_u16(addr: Address, offset: u16) -> u16 {
+    (read_config(addr, offset) >> ((offset & 0x2) * 8)) as u16
+}
+
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub address: Address,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    pub header_type: u8,
+    pub bars: Vec<(usize, Bar)>, // Номер регистра BAR и его содержимое
+    pub capabilities: Vec<(u8, u8)>, // Смещение и идентификатор возможности
+    pub interrupt_line: u8,
+    pub interrupt_pin: u8,
+    pub driver: Option<&'static str>,
+}
+
+impl Device {
+    pub fn is_bridge(&self) -> bool {
+        self.class == 0x06 && self.subclass == 0x04 && self.header_type & 0x7F == 1
+    }
+}
+
+// Размер BAR определяется записью единиц; на это время декодирование
+// адресов устройством отключается
+fn read_bars(addr: Address, count: usize) -> Vec<(usize, Bar)> {
+    let command = read_config(addr, COMMAND);
+    write_config(addr, COMMAND, command & !COMMAND_DECODE);
+
+    let size = |offset: u16| {
+        let value = read_config(addr, offset);
+        write_config(addr, offset, 0xFFFF_FFFF);
+        let mask = read_config(addr, offset);
+        write_config(addr, offset, value);
+        (value, mask)
+    };
+    let mut bars = Vec::new();
+    let mut index = 0;
+    while index < count {
+        let offset = BAR0 + index as u16 * 4;
+        let (low, low_mask) = size(offset);
+        let (value, mask, used) = if !bar_is_io(low) && bar_is_64bit(low) && index + 1 < count {
+            let (high, high_mask) = size(offset + 4);
+            (
+                (high as u64) << 32 | low as u64,
+                (high_mask as u64) << 32 | low_mask as u64,
+                2,
+            )
+        } else {
+            (low as u64, 0xFFFF_FFFF_0000_0000 | low_mask as u64, 1)
+        };
+        if let Some(bar) = decode_bar(value, mask) {
+            bars.push((index, bar));
+        }
+        index += used;
+    }
+
+    write_config(addr, COMMAND, command);
+    bars
+}
+
+fn read_capabilities(addr: Address) -> Vec<(u8, u8)> {
+    let mut capabilities = Vec::new();
+    if read_config(addr, COMMAND) & STATUS_CAPABILITIES == 0 {
+        return capabilities;
+    }
+    let mut offset = read_u8(addr, CAPABILITIES) & 0xFC;
+    // Ограничение защищает от зацикленного списка
+    while offset != 0 && capabilities.len() < MAX_CAPABILITIES {
+        let header = read_u16(addr, offset as u16);
+        capabilities.push((offset, header as u8));
+        offset = (header >> 8) as u8 & 0xFC;
+    }
+    capabilities
+}
+
+fn probe(addr: Address) -> Device {
+    let id = read_config(addr, VENDOR_ID);
+    let class = read_config(addr, CLASS);
+    let header_type = read_u8(addr, HEADER_TYPE);
+    let interrupt = read_config(addr, INTERRUPT);
+    let bar_count = match header_type & 0x7F {
+        0 => 6,
+        1 => 2,
+        _ => 0,
+    };
+    Device {
+        address: addr,
+        vendor_id: id as u16,
+        device_id: (id >> 16) as u16,
+        class: (class >> 24) as u8,
+        subclass: (class >> 16) as u8,
+        prog_if: (class >> 8) as u8,
+        revision: class as u8,
+        header_type,
+        bars: read_bars(addr, bar_count),
+        capabilities: read_capabilities(addr),
+        interrupt_line: interrupt as u8,
+        interrupt_pin: (interrupt >> 8) as u8,
+        driver: None,
+    }
+}
+
+fn present(addr: Address) -> bool {
+    read_u16(addr, VENDOR_ID) != 0xFFFF
+}
+
+// Рекурсивный обход: за мостами PCI-PCI лежат вторичные шины
+fn scan_bus(bus: u8, devices: &mut Vec<Device>, visited: &mut [bool; 256]) {
+    if visited[bus as usize] {
+        return;
+    }
+    visited[bus as usize] = true;
+    for device in 0..32 {
+        let first = Address {
+            bus,
+            device,
+            function: 0,
+        };
+        if !present(first) {
+            continue;
+        }
+        let functions = if read_u8(first, HEADER_TYPE) & 0x80 != 0 {
+            8
+        } else {
+            1
+        };
+        for function in 0..functions {
+            let addr = Address { function, ..first };
+            if !present(addr) {
+                continue;
+            }
+            let found = probe(addr);
+            let secondary = (read_config(addr, BUS_NUMBERS) >> 8) as u8;
+            let bridge = found.is_bridge();
+            devices.push(found);
+            if bridge {
+                scan_bus(secondary, devices, visited);
+            }
+        }
+    }
+}
+
+// Драйвер выбирается по идентификатору устройства или по классу
+#[derive(Debug, Clone, Copy)]
+pub enum Match {
+    Id { vendor: u16, device: u16 },
+    Class { class: u8, subclass: u8 },
+}
+
+#[derive(Clone, Copy)]
+pub struct Driver {
+    pub name: &'static str,
+    pub matches: &'static [Match],
+    pub probe: fn(&Device) -> Result<(), &'static str>,
+}
+
+impl Driver {
+    fn supports(&self, device: &Device) -> bool {
+        self.matches.iter().any(|m| match *m {
+            Match::Id { vendor, device: id } => {
+                device.vendor_id == vendor && device.device_id == id
+            }
+            Match::Class { class, subclass } => {
+                device.class == class && device.subclass == subclass
+            }
+        })
+    }
+}
+
+static DEVICES: Mutex<Vec<Device>> = Mutex::new(Vec::new());
+static DRIVERS: Mutex<Vec<Driver>> = Mutex::new(Vec::new());
+
+// Привязывает драйверы к свободным устройствам. Списки не заблокированы
+// во время `probe`, чтобы драйвер мог сам обращаться к модулю.
+fn bind_drivers() {
+    let drivers = DRIVERS.lock().clone();
+    let devices = DEVICES.lock().clone();
+    for device in devices.iter().filter(|device| device.driver.is_none()) {
+        let Some(driver) = drivers.iter().find(|driver| driver.supports(device)) else {
+            continue;
+        };
+        match (driver.probe)(device) {
+            Ok(()) => {
+                log::info!("PCI {}: bound to {}", device.address, driver.name);
+                if let Some(bound) = DEVICES
+                    .lock()
+                    .iter_mut()
+                    .find(|bound| bound.address == device.address)
+                {
+                    bound.driver = Some(driver.name);
+                }
+            }
+            Err(msg) => log::warn!("PCI {}: {}: {}", device.address, driver.name, msg),
+        }
+    }
+}
+
+// Драйвер сразу получает подходящие устройства, уже найденные при обходе
+pub fn register_driver(driver: Driver) {
+    DRIVERS.lock().push(driver);
+    bind_drivers();
+}
+
+// Убирает драйвер и отвязывает его устройства (для тестов: списки глобальные)
+#[cfg(test)]
+fn unregister_driver(name: &str) {
+    DRIVERS.lock().retain(|driver| driver.name != name);
+    for device in DEVICES.lock().iter_mut() {
+        if device.driver == Some(name) {
+            device.driver = None;
+        }
+    }
+}
+
+pub fn devices() -> Vec<Device> {
+    DEVICES.lock().clone()
+}
+
+pub fn init() {
+    // ECAM из таблицы MCFG (сегмент 0), иначе порты 0xCF8/0xCFC
+    let ecam = acpi::mcfg().and_then(|entries| entries.into_iter().find(|e| e.segment == 0));
+    if let Some(entry) = ecam {
+        *CONFIG.lock() = Access::Ecam(Box::new(Ecam {
+            base: entry.base,
+            start_bus: entry.start_bus,
+            end_bus: entry.end_bus,
+            buses: [0; 256],
+        }));
+    }
+
+    let mut devices = Vec::new();
+    let mut visited = [false; 256];
+    let host = Address {
+        bus: 0,
+        device: 0,
+        function: 0,
+    };
+    if read_u8(host, HEADER_TYPE) & 0x80 == 0 {
+        scan_bus(0, &mut devices, &mut visited);
+    } else {
+        // Несколько host-мостов: функция N обслуживает шину N
+        for function in 0..8 {
+            if present(Address { function, ..host }) {
+                scan_bus(function, &mut devices, &mut visited);
+            }
+        }
+    }
+
+    let count = devices.len();
+    *DEVICES.lock() = devices;
+    bind_drivers();
+    log::info!(
+        "PCI: {} devices ({})",
+        count,
+        if ecam.is_some() { "ECAM" } else { "port I/O" }
+    );
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "lspci",
+        aliases: &[],
+        description: "List PCI devices",
+        usage: "lspci [-v|--verbose]",
+        args: ArgSpec {
+            options: &[OptSpec {
+                name: "verbose",
+                short: Some('v'),
+                takes_value: false,
+            }],
+            min: 0,
+            max: 0,
+        },
+        handler: lspci_action,
+    });
+}
+
+fn lspci_action(io: &mut Io, args: &Args) -> i32 {
+    let verbose = args.flag("verbose");
+    for device in devices() {
+        let _ = write!(
+            io,
+            "{} {} [{:02x}{:02x}]: ",
+            device.address,
+            class_name(device.class, device.subclass, device.prog_if),
+            device.class,
+            device.subclass
+        );
+        if let Some(vendor) = vendor_name(device.vendor_id) {
+            let _ = write!(io, "{} ", vendor);
+        }
+        let _ = writeln!(
+            io,
+            "{:04x}:{:04x} (rev {:02x})",
+            device.vendor_id, device.device_id, device.revision
+        );
+        if verbose {
+            print_details(io, &device);
+        }
+    }
+    0
+}
+
+fn print_details(io: &mut Io, device: &Device) {
+    // Выводы INTA#–INTD#; прочие значения регистра некорректны
+    let pin = device.interrupt_pin;
+    let _ = match pin {
+        0 => Ok(()),
+        1..=4 => writeln!(
+            io,
+            "    interrupt: pin {}, IRQ {}",
+            (b'A' + pin - 1) as char,
+            device.interrupt_line
+        ),
+        _ => writeln!(
+            io,
+            "    interrupt: pin {:#04x}, IRQ {}",
+            pin, device.interrupt_line
+        ),
+    };
+    for &(index, bar) in &device.bars {
+        let _ = match bar {
+            Bar::Memory {
+                address,
+                size,
+                prefetchable,
+                wide,
+            } => writeln!(
+                io,
+                "    BAR{}: memory at {:#x} ({}-bit, {}prefetchable) [size={:#x}]",
+                index,
+                address,
+                if wide { 64 } else { 32 },
+                if prefetchable { "" } else { "non-" },
+                size
+            ),
+            Bar::Io { port, size } => {
+                writeln!(
+                    io,
+                    "    BAR{}: I/O ports at {:#x} [size={:#x}]",
+                    index, port, size
+                )
+            }
+        };
+    }
+    for &(offset, id) in &device.capabilities {
+        let _ = writeln!(
+            io,
+            "    capability [{:02x}]: {} ({:#04x})",
+            offset,
+            capability_name(id),
+            id
+        );
+    }
+    if let Some(driver) = device.driver {
+        let _ = writeln!(io, "    driver: {}", driver);
+    }
+    if let Some(framebuffer) = vga::framebuffer().filter(|fb| fb.device == device.address) {
+        let _ = writeln!(
+            io,
+            "    framebuffer: {:#x} mapped at {:#x} [size={:#x}]",
+            framebuffer.physical, framebuffer.virt, framebuffer.size
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{devices, register_driver, unregister_driver, Device, Driver, Match};
+    use alloc::boxed::Box;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    static PROBED: AtomicBool = AtomicBool::new(false);
+
+    fn probe_host_bridge(_device: &Device) -> Result<(), &'static str> {
+        PROBED.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // В QEMU первым устройством всегда идёт host-мост 00:00.0
+    #[test_case]
+    fn host_bridge_is_found() {
+        let devices = devices();
+        let host = devices.first().unwrap();
+        assert_eq!((host.address.bus, host.address.device), (0, 0));
+        assert_eq!((host.class, host.subclass), (0x06, 0x00));
+    }
+
+    fn probe_any(_device: &Device) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    #[test_case]
+    fn drivers_bind_by_class() {
+        register_driver(Driver {
+            name: "test-host-bridge",
+            matches: &[Match::Class {
+                class: 0x06,
+                subclass: 0x00,
+            }],
+            probe: probe_host_bridge,
+        });
+        assert!(PROBED.load(Ordering::Relaxed));
+        assert_eq!(devices()[0].driver, Some("test-host-bridge"));
+        unregister_driver("test-host-bridge");
+        assert_eq!(devices()[0].driver, None);
+    }
+
+    #[test_case]
+    fn drivers_bind_by_id() {
+        // Любое свободное устройство с единственной в системе парой ID
+        let same_id =
+            |a: &Device, b: &Device| a.vendor_id == b.vendor_id && a.device_id == b.device_id;
+        let all = devices();
+        let target = all
+            .iter()
+            .find(|device| {
+                device.driver.is_none()
+                    && all.iter().filter(|other| same_id(device, other)).count() == 1
+            })
+            .unwrap();
+        let matches = Box::leak(Box::new([Match::Id {
+            vendor: target.vendor_id,
+            device: target.device_id,
+        }]));
+        register_driver(Driver {
+            name: "test-by-id",
+            matches,
+            probe: probe_any,
+        });
+        for device in &devices() {
+            if device.address == target.address {
+                assert_eq!(device.driver, Some("test-by-id"));
+            } else {
+                assert_ne!(device.driver, Some("test-by-id"));
+            }
+        }
+        unregister_driver("test-by-id");
+    }
+}
@@ -3,44 +3,115 @@ use x86_64::instructions::port::Port;
 pub const PIC_1_OFFSET: u8 = 32;
 pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
 
+const CMD_INIT: u8 = 0x11; // ICW1: начальная команда, будет ICW4
+const CMD_EOI: u8 = 0x20;
+const MODE_8086: u8 = 0x01; // ICW4: режим 8086/88
+const CASCADE_IRQ: u8 = 2; // Линия ведущего контроллера, к которой подключён ведомый
+
+struct Pic {
+    offset: u8,
+    command: Port<u8>,
+    data: Port<u8>,
+}
+
+impl Pic {
+    // Вектор относится к этому контроллеру
+    fn handles_interrupt(&self, vector: u8) -> bool {
+        (self.offset..self.offset + 8).contains(&vector)
+    }
+
+    unsafe fn end_of_interrupt(&mut self) {
+        self.command.write(CMD_EOI);
+    }
+}
+
 pub struct ChainedPics {
-    master_command: Port<u8>,
-    master_data: Port<u8>,
-    slave_command: Port<u8>,
-    slave_data: Port<u8>,
+    master: Pic,
+    slave: Pic,
+}
+
+// Пауза между командами: запись в неиспользуемый порт 0x80
+unsafe fn io_wait() {
+    Port::<u8>::new(0x80).write(0);
 }
 
 impl ChainedPics {
-    pub const unsafe fn new(_offset1: u8, _offset2: u8) -> Self {
+    pub const unsafe fn new(offset1: u8, offset2: u8) -> Self {
         ChainedPics {
-            master_command: Port::new(0x20),
-            master_data: Port::new(0x21),
-            slave_command: Port::new(0xA0),
-            slave_data: Port::new(0xA1),
+            master: Pic {
+                offset: offset1,
+                command: Port::new(0x20),
+                data: Port::new(0x21),
+            },
+            slave: Pic {
+                offset: offset2,
+                command: Port::new(0xA0),
+                data: Port::new(0xA1),
+            },
         }
     }
 
+    // Переносит IRQ 0–15 на векторы `offset1`/`offset2` и маскирует все линии,
+    // кроме каскада. Нужные линии открывает `unmask`.
     pub unsafe fn initialize(&mut self) {
-        let icw1 = 0x11; // Начальная команда
-        let icw4 = 0x01; // 8086/88 (MCS-80/85) mode
+        self.master.command.write(CMD_INIT);
+        io_wait();
+        self.slave.command.write(CMD_INIT);
+        io_wait();
+
+        self.master.data.write(self.master.offset);
+        io_wait();
+        self.slave.data.write(self.slave.offset);
+        io_wait();
 
-        self.master_command.write(icw1);
-        self.slave_command.write(icw1);
+        self.master.data.write(1 << CASCADE_IRQ); // Ведомый подключён к IRQ2
+        io_wait();
+        self.slave.data.write(CASCADE_IRQ); // Номер линии ведомого
+        io_wait();
 
-        self.master_data.write(PIC_1_OFFSET);
-        self.slave_data.write(PIC_2_OFFSET);
+        self.master.data.write(MODE_8086);
+        io_wait();
+        self.slave.data.write(MODE_8086);
+        io_wait();
 
-        self.master_data.write(4); // Указывает на подключение Slave PIC на IRQ2
-        self.slave_data.write(2); // Указывает на линию Slave PIC
+        self.write_masks(!(1 << CASCADE_IRQ), 0xFF);
+    }
+
+    pub unsafe fn read_masks(&mut self) -> (u8, u8) {
+        (self.master.data.read(), self.slave.data.read())
+    }
 
-        self.master_data.write(icw4);
-        self.slave_data.write(icw4);
+    pub unsafe fn write_masks(&mut self, master: u8, slave: u8) {
+        self.master.data.write(master);
+        self.slave.data.write(slave);
+    }
+
+    pub unsafe fn unmask(&mut self, irq: u8) {
+        let (master, slave) = self.read_masks();
+        if irq < 8 {
+            self.write_masks(master & !(1 << irq), slave);
+        } else {
+            self.write_masks(master, slave & !(1 << (irq - 8)));
+        }
     }
 
-    pub unsafe fn notify_end_of_interrupt(&mut self, irq: u8) {
-        if irq >= 8 {
-            self.slave_command.write(0x20);
+    // Маскирует все линии обоих контроллеров: прерывания идут через IOAPIC
+    pub unsafe fn disable(&mut self) {
+        self.write_masks(0xFF, 0xFF);
+    }
+
+    pub fn handles_interrupt(&self, vector: u8) -> bool {
+        self.master.handles_interrupt(vector) || self.slave.handles_interrupt(vector)
+    }
+
+    // `vector` — номер вектора, а не IRQ. Прерывание ведомого контроллера
+    // прошло и через ведущий, подтверждаем на обоих.
+    pub unsafe fn notify_end_of_interrupt(&mut self, vector: u8) {
+        if self.slave.handles_interrupt(vector) {
+            self.slave.end_of_interrupt();
+        }
+        if self.handles_interrupt(vector) {
+            self.master.end_of_interrupt();
         }
-        self.master_command.write(0x20);
     }
 }
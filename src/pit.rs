@@ -1,4 +1,8 @@
+use crate::constants::TICKS_PER_SECOND;
+use crate::datetime::TICKS;
+use core::sync::atomic::Ordering;
 use x86_64::instructions::port::Port;
+use x86_64::instructions::{hlt, interrupts};
 
 pub fn init_pit() {
     let frequency: u16 = 1193; // Частота таймера ~1мс (1193182 / 1000)
@@ -12,3 +16,21 @@ pub fn init_pit() {
         data_port.write((frequency >> 8) as u8); // Старший байт
     }
 }
+
+// Ждёт до `ms` миллисекунд, пока `busy` возвращает true. Возвращает false по
+// таймауту. Время идёт по прерываниям таймера: при выключенных прерываниях
+// ожидание сразу завершается неудачей.
+pub fn wait_while(ms: usize, mut busy: impl FnMut() -> bool) -> bool {
+    let end = TICKS.load(Ordering::Relaxed) + ms * TICKS_PER_SECOND / 1000;
+    while busy() {
+        if TICKS.load(Ordering::Relaxed) >= end || !interrupts::are_enabled() {
+            return false;
+        }
+        hlt();
+    }
+    true
+}
+
+pub fn sleep_ms(ms: usize) {
+    wait_while(ms, || true);
+}
@@ -0,0 +1,98 @@
+use crate::memory::phys_to_virt;
+use crate::pit::{sleep_ms, wait_while};
+use crate::serial::COM1;
+use crate::{acpi, log};
+use core::arch::asm;
+use x86_64::instructions::interrupts::{self, without_interrupts};
+use x86_64::instructions::port::Port;
+use x86_64::instructions::tables::lidt;
+use x86_64::structures::DescriptorTablePointer;
+use x86_64::VirtAddr;
+
+const RESET_TIMEOUT_MS: usize = 500; // Сколько ждём после каждого способа
+const KBC_TIMEOUT_MS: usize = 100;
+
+// Контроллер клавиатуры 8042
+const KBC_STATUS: u16 = 0x64;
+const KBC_INPUT_FULL: u8 = 0x02;
+const KBC_PULSE_RESET: u8 = 0xFE; // Импульс на линии сброса процессора
+
+const RESET_CONTROL: u16 = 0xCF9;
+
+// Сброс через регистр из FADT: память или порт ввода-вывода
+fn acpi_reset() -> bool {
+    let Some((register, value)) = acpi::reset_register() else {
+        return false;
+    };
+    match register.space {
+        0 => unsafe { phys_to_virt(register.address).write_volatile(value) },
+        1 => unsafe { Port::new(register.address as u16).write(value) },
+        space => {
+            log::warn!("reboot: unsupported ACPI reset register space {}", space);
+            return false;
+        }
+    }
+    true
+}
+
+fn keyboard_controller_reset() -> bool {
+    let mut status: Port<u8> = Port::new(KBC_STATUS);
+    // Команду можно отправить только при пустом входном буфере контроллера
+    if !wait_while(
+        KBC_TIMEOUT_MS,
+        || unsafe { status.read() } & KBC_INPUT_FULL != 0,
+    ) {
+        log::warn!("reboot: keyboard controller is busy");
+        return false;
+    }
+    unsafe { status.write(KBC_PULSE_RESET) };
+    true
+}
+
+fn reset_control_reset() {
+    let mut port: Port<u8> = Port::new(RESET_CONTROL);
+    unsafe {
+        port.write(0x02); // Выбираем полный сброс
+        port.write(0x06); // и выполняем его
+    }
+}
+
+// Пустая IDT: любое прерывание приводит к тройной ошибке и сбросу процессора
+fn triple_fault() -> ! {
+    let idt = DescriptorTablePointer {
+        limit: 0,
+        base: VirtAddr::new(0),
+    };
+    unsafe {
+        lidt(&idt);
+        asm!("int3", options(noreturn));
+    }
+}
+
+// Перезагружает машину, пробуя способы по очереди: регистр сброса ACPI,
+// контроллер клавиатуры, порт 0xCF9 и, наконец, тройную ошибку
+pub fn reboot() -> ! {
+    log::info!("Rebooting");
+    // Файловая система живёт в памяти, сбрасывать на диск нечего; дожидаемся
+    // вывода в COM1 и записываем кэши процессора в память
+    without_interrupts(|| COM1.lock().flush());
+    unsafe { asm!("wbinvd", options(nostack, preserves_flags)) };
+
+    // Таймауты отсчитываются по прерываниям таймера
+    interrupts::enable();
+
+    if acpi_reset() {
+        sleep_ms(RESET_TIMEOUT_MS);
+        log::warn!("reboot: ACPI reset register did not work");
+    }
+    if keyboard_controller_reset() {
+        sleep_ms(RESET_TIMEOUT_MS);
+        log::warn!("reboot: keyboard controller reset did not work");
+    }
+    reset_control_reset();
+    sleep_ms(RESET_TIMEOUT_MS);
+    log::warn!("reboot: reset control register did not work");
+
+    interrupts::disable();
+    triple_fault();
+}
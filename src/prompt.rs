@@ -0,0 +1,166 @@
+use crate::constants::{COLS, MAX_PROMPT_LEN};
+use crate::datetime::{get_date, get_time};
+use crate::fs::FS;
+use crate::shell::{get_var, last_status};
+use crate::theme;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::iter::Peekable;
+use core::str::Chars;
+
+// Символ приглашения и его цвет
+pub type Cell = (u8, u8);
+
+// Цвета ANSI (0–7) в порядке цветов VGA
+const ANSI_TO_VGA: [u8; 8] = [0x0, 0x4, 0x2, 0x6, 0x1, 0x5, 0x3, 0x7];
+
+// Раскрывает PS1 в символы с цветами. Поддерживаются escape-последовательности
+// в стиле bash:
+//   \u пользователь ($USER)    \h имя машины (/etc/hostname)
+//   \w текущий каталог        \W последний компонент каталога
+//   \t время HH:MM:SS          \A время HH:MM        \d дата DD.MM.YYYY
+//   \? код завершения          \$ символ `$`         \n перевод строки
+//   \\ обратная косая черта   \e или \033 — начало цвета ANSI `\e[1;32m`
+// `\[` и `\]` допускаются для совместимости с bash и ничего не выводят.
+pub fn render(ps1: &str) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    let mut color = theme::text();
+    let mut chars = ps1.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let text = match c {
+            '\x1b' => {
+                color = sgr(&mut chars, color);
+                continue;
+            }
+            '\\' => match chars.next() {
+                Some('u') => get_var("USER").unwrap_or_else(|| String::from("root")),
+                Some('h') => hostname(),
+                Some('w') => cwd(false),
+                Some('W') => cwd(true),
+                Some('t') => {
+                    let (h, m, s) = get_time();
+                    format!("{:02}:{:02}:{:02}", h, m, s)
+                }
+                Some('A') => {
+                    let (h, m, _) = get_time();
+                    format!("{:02}:{:02}", h, m)
+                }
+                Some('d') => {
+                    let (d, m, y) = get_date();
+                    format!("{:02}.{:02}.{:04}", d, m, y)
+                }
+                Some('?') => last_status().to_string(),
+                Some('$') => String::from("$"),
+                Some('\\') => String::from("\\"),
+                Some('[' | ']') => continue,
+                Some('n') => {
+                    // Дополняем строку пустыми клетками до конца экрана
+                    while cells.len() % COLS != 0 || cells.is_empty() {
+                        cells.push((0, color));
+                    }
+                    continue;
+                }
+                Some('e') => {
+                    color = sgr(&mut chars, color);
+                    continue;
+                }
+                Some('0') if chars.peek() == Some(&'3') => {
+                    chars.next();
+                    if chars.next_if_eq(&'3').is_some() {
+                        color = sgr(&mut chars, color);
+                    }
+                    continue;
+                }
+                Some(other) => format!("\\{}", other),
+                None => String::from("\\"),
+            },
+            c => String::from(c),
+        };
+
+        for byte in text.bytes() {
+            let byte = if byte.is_ascii() { byte } else { b'?' };
+            cells.push((byte, color));
+        }
+    }
+
+    cells.truncate(MAX_PROMPT_LEN);
+    cells
+}
+
+// Разбирает `[...m` после ESC и возвращает новый цвет.
+// Поддерживаются 0 (сброс), 1 (яркий), 30–37, 39, 40–47, 49 и 90–97.
+fn sgr(chars: &mut Peekable<Chars>, color: u8) -> u8 {
+    if chars.next_if_eq(&'[').is_none() {
+        return color;
+    }
+    let mut params = String::new();
+    loop {
+        match chars.next() {
+            Some('m') => break,
+            Some(c) if c.is_ascii_digit() || c == ';' => params.push(c),
+            // Другие последовательности не поддерживаются
+            _ => return color,
+        }
+    }
+
+    let default = theme::text();
+    let mut color = color;
+    for param in params.split(';') {
+        let code = param.parse::<u8>().unwrap_or(0);
+        color = match code {
+            0 => default,
+            1 => color | 0x08,
+            30..=37 => (color & 0xF8) | ANSI_TO_VGA[(code - 30) as usize],
+            39 => (color & 0xF0) | (default & 0x0F),
+            40..=47 => (color & 0x0F) | ANSI_TO_VGA[(code - 40) as usize] << 4,
+            49 => (color & 0x0F) | (default & 0xF0),
+            90..=97 => (color & 0xF0) | ANSI_TO_VGA[(code - 90) as usize] | 0x08,
+            _ => color,
+        };
+    }
+    color
+}
+
+fn hostname() -> String {
+    let guard = FS.lock();
+    let name = guard
+        .as_ref()
+        .and_then(|fs| fs.read_file("/etc/hostname").ok())
+        .map(|data| {
+            String::from_utf8_lossy(data)
+                .lines()
+                .next()
+                .unwrap_or("")
+                .to_string()
+        });
+    match name {
+        Some(name) if !name.is_empty() => name,
+        _ => String::from("neonforge"),
+    }
+}
+
+// Текущий каталог; домашний каталог ($HOME) сокращается до `~`
+fn cwd(basename: bool) -> String {
+    let cwd = match FS.lock().as_ref() {
+        Some(fs) => String::from(fs.cwd()),
+        None => String::from("/"),
+    };
+    if let Some(home) = get_var("HOME").filter(|home| home.len() > 1) {
+        if cwd == home {
+            return String::from("~");
+        }
+        if !basename {
+            if let Some(rest) = cwd.strip_prefix(home.as_str()) {
+                if rest.starts_with('/') {
+                    return format!("~{}", rest);
+                }
+            }
+        }
+    }
+    if basename && cwd != "/" {
+        return String::from(cwd.rsplit('/').next().unwrap_or("/"));
+    }
+    cwd
+}
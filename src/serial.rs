@@ -0,0 +1,386 @@
+use crate::commands::{register, Command, Io};
+use crate::constants::{SERIAL_BAUD, SERIAL_RX_SIZE};
+use crate::eprintln;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+use neonforge_core::args::{ArgSpec, Args, OptSpec};
+use neonforge_core::keyboard::Key;
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::Port;
+
+pub const COM1_BASE: u16 = 0x3F8;
+const UART_CLOCK: u32 = 115200; // Частота при делителе 1
+
+// Биты регистра состояния линии (LSR)
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_TX_EMPTY: u8 = 0x20;
+const LSR_TX_IDLE: u8 = 0x40; // Передатчик и его FIFO пусты
+
+#[derive(Debug, Clone, Copy)]
+pub struct SerialConfig {
+    pub baud: u32,
+    pub fifo: bool,
+}
+
+// Последовательный порт 16550
+pub struct SerialPort {
+    base: u16,
+    config: SerialConfig,
+    present: bool,
+}
+
+impl SerialPort {
+    pub const fn new(base: u16) -> Self {
+        SerialPort {
+            base,
+            config: SerialConfig {
+                baud: SERIAL_BAUD,
+                fifo: true,
+            },
+            present: false,
+        }
+    }
+
+    fn write_reg(&self, offset: u16, value: u8) {
+        unsafe { Port::new(self.base + offset).write(value) }
+    }
+
+    fn read_reg(&self, offset: u16) -> u8 {
+        unsafe { Port::new(self.base + offset).read() }
+    }
+
+    pub fn config(&self) -> SerialConfig {
+        self.config
+    }
+
+    pub fn present(&self) -> bool {
+        self.present
+    }
+
+    // Настраивает порт: 8 бит, без чётности, 1 стоповый бит, прерывание по приёму.
+    // Возвращает ошибку, если порт не прошёл проверку в режиме петли.
+    pub fn init(&mut self, config: SerialConfig) -> Result<(), &'static str> {
+        if config.baud == 0
+            || !UART_CLOCK.is_multiple_of(config.baud)
+            || UART_CLOCK / config.baud > 0xFFFF
+        {
+            return Err("unsupported baud rate");
+        }
+        let divisor = (UART_CLOCK / config.baud) as u16;
+
+        self.write_reg(1, 0x00); // Отключаем прерывания
+        self.write_reg(3, 0x80); // DLAB: доступ к делителю
+        self.write_reg(0, divisor as u8);
+        self.write_reg(1, (divisor >> 8) as u8);
+        self.write_reg(3, 0x03); // 8N1
+
+        // FIFO: включить и очистить, прерывание при 1 байте, чтобы не ждать таймаута
+        self.write_reg(2, if config.fifo { 0x07 } else { 0x00 });
+
+        // Проверка в режиме петли: отправленный байт должен вернуться
+        self.write_reg(4, 0x1E);
+        self.write_reg(0, 0xAE);
+        self.present = self.read_reg(0) == 0xAE;
+
+        self.write_reg(4, 0x0B); // DTR, RTS, OUT2 (OUT2 пропускает прерывания к PIC)
+        if self.present {
+            self.write_reg(1, 0x01); // Прерывание при получении данных
+        }
+        self.config = config;
+        if self.present {
+            Ok(())
+        } else {
+            Err("loopback test failed")
+        }
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        if !self.present {
+            return;
+        }
+        // Ждём, пока освободится регистр передатчика
+        while self.read_reg(5) & LSR_TX_EMPTY == 0 {
+            core::hint::spin_loop();
+        }
+        self.write_reg(0, byte);
+    }
+
+    // Ждёт, пока все байты из FIFO передатчика уйдут в линию
+    pub fn flush(&mut self) {
+        if !self.present {
+            return;
+        }
+        while self.read_reg(5) & LSR_TX_IDLE == 0 {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl fmt::Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+pub static COM1: Mutex<SerialPort> = Mutex::new(SerialPort::new(COM1_BASE));
+
+// Принятые байты. Заполняется обработчиком IRQ4, читается главным циклом.
+struct RxQueue {
+    data: [u8; SERIAL_RX_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl RxQueue {
+    const fn new() -> Self {
+        RxQueue {
+            data: [0; SERIAL_RX_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    // При переполнении новые байты отбрасываются
+    fn push(&mut self, byte: u8) {
+        if self.len < SERIAL_RX_SIZE {
+            self.data[(self.head + self.len) % SERIAL_RX_SIZE] = byte;
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.data[self.head];
+        self.head = (self.head + 1) % SERIAL_RX_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+pub fn _print(args: fmt::Arguments) {
+    without_interrupts(|| {
+        let _ = COM1.lock().write_fmt(args);
+    });
+}
+
+// Вывод только в COM1, минуя экран (используется тестами)
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => ($crate::serial::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($($arg:tt)*) => ($crate::serial_print!("{}\n", format_args!($($arg)*)));
+}
+
+static RX: Mutex<RxQueue> = Mutex::new(RxQueue::new());
+
+// Последовательная консоль: вывод терминала дублируется в COM1, ввод из COM1
+// обрабатывается как нажатия клавиш
+static CONSOLE: AtomicBool = AtomicBool::new(true);
+
+pub fn console_enabled() -> bool {
+    CONSOLE.load(Ordering::Relaxed) && COM1.lock().present()
+}
+
+pub fn init() {
+    // Журнал ещё не готов, поэтому результат можно узнать командой `serial`
+    let _ = COM1.lock().init(SerialConfig {
+        baud: SERIAL_BAUD,
+        fifo: true,
+    });
+}
+
+// Вызывается из обработчика IRQ4. Порт читается напрямую, без блокировки COM1,
+// которую может держать прерванный код.
+pub fn handle_interrupt() {
+    let mut line_status: Port<u8> = Port::new(COM1_BASE + 5);
+    let mut data: Port<u8> = Port::new(COM1_BASE);
+    let mut rx = RX.lock();
+    unsafe {
+        while line_status.read() & LSR_DATA_READY != 0 {
+            rx.push(data.read());
+        }
+    }
+}
+
+fn read_byte() -> Option<u8> {
+    without_interrupts(|| RX.lock().pop())
+}
+
+// Пишет текст в COM1, если включена последовательная консоль
+pub fn mirror(s: &str) {
+    if console_enabled() {
+        without_interrupts(|| {
+            let _ = COM1.lock().write_str(s);
+        });
+    }
+}
+
+// Разбор ввода терминала: управляющие символы и последовательности
+// ESC [ ... для стрелок, Home/End, Insert/Delete
+#[derive(Clone, Copy)]
+enum DecodeState {
+    Normal,
+    Escape,
+    Csi(u8), // Собранный числовой параметр
+}
+
+static DECODE_STATE: Mutex<DecodeState> = Mutex::new(DecodeState::Normal);
+
+fn decode(state: &mut DecodeState, byte: u8) -> Option<Key> {
+    match (*state, byte) {
+        (DecodeState::Normal, 0x1B) => {
+            *state = DecodeState::Escape;
+            None
+        }
+        (DecodeState::Normal, b'\r' | b'\n') => Some(Key::Enter),
+        (DecodeState::Normal, b'\t') => Some(Key::Tab),
+        (DecodeState::Normal, 0x08 | 0x7F) => Some(Key::Backspace),
+        (DecodeState::Normal, 0x01..=0x1A) => Some(Key::Ctrl((b'a' + byte - 1) as char)),
+        (DecodeState::Normal, 0x20..=0x7E) => Some(Key::Char(byte as char)),
+        (DecodeState::Normal, _) => None,
+        (DecodeState::Escape, b'[' | b'O') => {
+            *state = DecodeState::Csi(0);
+            None
+        }
+        (DecodeState::Escape, _) => {
+            *state = DecodeState::Normal;
+            Some(Key::Escape)
+        }
+        (DecodeState::Csi(param), b'0'..=b'9') => {
+            *state = DecodeState::Csi(param.saturating_mul(10).saturating_add(byte - b'0'));
+            None
+        }
+        (DecodeState::Csi(param), _) => {
+            *state = DecodeState::Normal;
+            match (byte, param) {
+                (b'A', _) => Some(Key::Up),
+                (b'B', _) => Some(Key::Down),
+                (b'C', _) => Some(Key::Right),
+                (b'D', _) => Some(Key::Left),
+                (b'H', _) | (b'~', 1 | 7) => Some(Key::Home),
+                (b'F', _) | (b'~', 4 | 8) => Some(Key::End),
+                (b'~', 2) => Some(Key::Insert),
+                (b'~', 3) => Some(Key::Delete),
+                _ => None,
+            }
+        }
+    }
+}
+
+// Следующая клавиша, полученная через последовательную консоль
+pub fn read_key() -> Option<Key> {
+    if !CONSOLE.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut state = DECODE_STATE.lock();
+    while let Some(byte) = read_byte() {
+        if let Some(key) = decode(&mut state, byte) {
+            return Some(key);
+        }
+    }
+    None
+}
+
+pub fn register_commands() {
+    register(Command {
+        name: "serial",
+        aliases: &[],
+        description: "Show or configure the COM1 serial port",
+        usage: "serial [--baud N] [--fifo on|off] [--console on|off]",
+        args: ArgSpec {
+            options: &[
+                OptSpec {
+                    name: "baud",
+                    short: Some('b'),
+                    takes_value: true,
+                },
+                OptSpec {
+                    name: "fifo",
+                    short: None,
+                    takes_value: true,
+                },
+                OptSpec {
+                    name: "console",
+                    short: None,
+                    takes_value: true,
+                },
+            ],
+            min: 0,
+            max: 0,
+        },
+        handler: serial_action,
+    });
+}
+
+fn parse_switch(name: &str, value: &str) -> Result<bool, i32> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => {
+            eprintln!("serial: --{} expects on or off", name);
+            Err(2)
+        }
+    }
+}
+
+fn serial_action(io: &mut Io, args: &Args) -> i32 {
+    let mut config = COM1.lock().config();
+    if let Some(baud) = args.value("baud") {
+        match baud.parse::<u32>() {
+            Ok(baud) => config.baud = baud,
+            Err(_) => {
+                eprintln!("serial: invalid baud rate: {}", baud);
+                return 2;
+            }
+        }
+    }
+    if let Some(value) = args.value("fifo") {
+        match parse_switch("fifo", value) {
+            Ok(fifo) => config.fifo = fifo,
+            Err(status) => return status,
+        }
+    }
+    if let Some(value) = args.value("console") {
+        match parse_switch("console", value) {
+            Ok(console) => CONSOLE.store(console, Ordering::Relaxed),
+            Err(status) => return status,
+        }
+    }
+
+    if args.value("baud").is_some() || args.value("fifo").is_some() {
+        if let Err(msg) = without_interrupts(|| COM1.lock().init(config)) {
+            eprintln!("serial: {}", msg);
+            return 1;
+        }
+    }
+
+    // Блокировку не держим во время вывода: консоль сама пишет в COM1
+    let (config, present) = {
+        let port = COM1.lock();
+        (port.config(), port.present())
+    };
+    let on_off = |flag: bool| if flag { "on" } else { "off" };
+    let _ = writeln!(
+        io,
+        "COM1 at {:#x}: {}",
+        COM1_BASE,
+        if present { "present" } else { "not found" }
+    );
+    let _ = writeln!(io, "baud     {}", config.baud);
+    let _ = writeln!(io, "fifo     {}", on_off(config.fifo));
+    let _ = writeln!(io, "console  {}", on_off(CONSOLE.load(Ordering::Relaxed)));
+    0
+}
@@ -0,0 +1,561 @@
+use crate::commands::{self, register, Command, Io};
+use crate::console::Console;
+use crate::constants::{AUTOEXEC_PATH, CONFIG_PATH, MAX_SCRIPT_DEPTH, MSG};
+use crate::fs::FS;
+use crate::{eprintln, log};
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use neonforge_core::args::{ArgSpec, Args, OptSpec};
+use neonforge_core::shell::{
+    assignment, is_name, Connector, List, Node, Parser, SimpleCommand, Word,
+};
+use spin::Mutex;
+
+struct Variable {
+    value: String,
+    exported: bool,
+}
+
+static VARIABLES: Mutex<BTreeMap<String, Variable>> = Mutex::new(BTreeMap::new());
+static ALIASES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+static LAST_STATUS: AtomicI32 = AtomicI32::new(0); // $?
+static POSITIONAL: Mutex<Vec<String>> = Mutex::new(Vec::new()); // $0, $1, ...
+static SCRIPT_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+pub fn get_var(name: &str) -> Option<String> {
+    VARIABLES.lock().get(name).map(|var| var.value.clone())
+}
+
+pub fn set_var(name: &str, value: &str) {
+    let mut vars = VARIABLES.lock();
+    match vars.get_mut(name) {
+        Some(var) => var.value = String::from(value),
+        None => {
+            vars.insert(
+                String::from(name),
+                Variable {
+                    value: String::from(value),
+                    exported: false,
+                },
+            );
+        }
+    }
+}
+
+// Строка приглашения: переменная PS1 или MSG по умолчанию
+pub fn prompt() -> String {
+    get_var("PS1").unwrap_or_else(|| String::from_utf8_lossy(MSG).into_owned())
+}
+
+// Имена псевдонимов (для дополнения по Tab)
+pub fn alias_names() -> Vec<String> {
+    ALIASES.lock().keys().cloned().collect()
+}
+
+pub fn last_status() -> i32 {
+    LAST_STATUS.load(Ordering::Relaxed)
+}
+
+fn lookup(name: &str) -> String {
+    match name {
+        "?" => last_status().to_string(),
+        "#" => POSITIONAL.lock().len().saturating_sub(1).to_string(),
+        _ => match name.parse::<usize>() {
+            Ok(index) => POSITIONAL.lock().get(index).cloned().unwrap_or_default(),
+            Err(_) => get_var(name).unwrap_or_default(),
+        },
+    }
+}
+
+fn alias(name: &str) -> Option<String> {
+    ALIASES.lock().get(name).cloned()
+}
+
+fn expand(word: &Word) -> String {
+    word.expand(&lookup)
+}
+
+// Разбирает и выполняет текст по одной команде верхнего уровня, чтобы
+// `alias` действовал на следующие строки
+fn run_text(text: &str, out: &mut dyn Write) -> Result<i32, String> {
+    let mut parser = Parser::new(text, &alias)?;
+    let mut status = 0;
+    while let Some(list) = parser.next_command()? {
+        status = run_list(&list, out);
+    }
+    Ok(status)
+}
+
+// Выполняет строку: команды, соединённые `;`, `&&`, `||` и `|`, с
+// перенаправлением в файлы и блоками if/while/for. Возвращает код
+// завершения последней команды.
+pub fn run(line: &str) -> i32 {
+    match run_text(line, &mut Console::new()) {
+        Ok(status) => status,
+        Err(msg) => {
+            eprintln!("Error: {}", msg);
+            LAST_STATUS.store(2, Ordering::Relaxed);
+            2
+        }
+    }
+}
+
+// Выполняет скрипт из файловой системы. `args` доступны в нём как $1, $2, ...
+pub fn run_script(path: &str, args: &[&str], out: &mut dyn Write) -> i32 {
+    let text = match read_file(path) {
+        Ok(text) => text,
+        Err(msg) => {
+            eprintln!("{}: {}", path, msg);
+            return 127;
+        }
+    };
+    if SCRIPT_DEPTH.load(Ordering::Relaxed) >= MAX_SCRIPT_DEPTH {
+        eprintln!("{}: too many nested scripts", path);
+        return 1;
+    }
+
+    let mut positional = vec![String::from(path)];
+    positional.extend(args.iter().map(|arg| String::from(*arg)));
+    let saved = core::mem::replace(&mut *POSITIONAL.lock(), positional);
+    SCRIPT_DEPTH.fetch_add(1, Ordering::Relaxed);
+
+    let status = match run_text(&text, out) {
+        Ok(status) => status,
+        Err(msg) => {
+            eprintln!("{}: Error: {}", path, msg);
+            LAST_STATUS.store(2, Ordering::Relaxed);
+            2
+        }
+    };
+
+    SCRIPT_DEPTH.fetch_sub(1, Ordering::Relaxed);
+    *POSITIONAL.lock() = saved;
+    status
+}
+
+// Читает настройки оболочки: псевдонимы, PS1, цвета, раскладку
+pub fn load_config() {
+    if read_file(CONFIG_PATH).is_err() {
+        log::warn!("{} not found, using default settings", CONFIG_PATH);
+        return;
+    }
+    run_script(CONFIG_PATH, &[], &mut Console::new());
+}
+
+// Запускает скрипт автозагрузки, если он есть
+pub fn autoexec() {
+    run_if_exists(AUTOEXEC_PATH);
+}
+
+fn run_if_exists(path: &str) {
+    if read_file(path).is_ok() {
+        run_script(path, &[], &mut Console::new());
+    }
+}
+
+fn run_list(list: &List, out: &mut dyn Write) -> i32 {
+    let mut status = 0;
+    for (connector, node) in list {
+        let run = match connector {
+            Connector::Always => true,
+            Connector::And => status == 0,
+            Connector::Or => status != 0,
+        };
+        if run {
+            status = run_node(node, out);
+            LAST_STATUS.store(status, Ordering::Relaxed);
+        }
+    }
+    status
+}
+
+fn run_node(node: &Node, out: &mut dyn Write) -> i32 {
+    match node {
+        Node::Pipeline(commands) => run_pipeline(commands, out),
+        Node::If {
+            branches,
+            otherwise,
+        } => {
+            for (condition, body) in branches {
+                if run_list(condition, out) == 0 {
+                    return run_list(body, out);
+                }
+            }
+            match otherwise {
+                Some(body) => run_list(body, out),
+                None => 0,
+            }
+        }
+        Node::While { condition, body } => {
+            let mut status = 0;
+            while run_list(condition, out) == 0 {
+                status = run_list(body, out);
+            }
+            status
+        }
+        Node::For { name, items, body } => {
+            // Значения без кавычек разбиваются по пробелам: `for x in $LIST`
+            let mut values = Vec::new();
+            for item in items {
+                let value = expand(item);
+                if item.quoted {
+                    values.push(value);
+                } else {
+                    values.extend(value.split_whitespace().map(String::from));
+                }
+            }
+
+            let mut status = 0;
+            for value in values {
+                set_var(name, &value);
+                status = run_list(body, out);
+            }
+            status
+        }
+    }
+}
+
+// Команды конвейера выполняются по очереди: вывод каждой собирается
+// в строку и становится вводом следующей.
+fn run_pipeline(pipeline: &[SimpleCommand], out: &mut dyn Write) -> i32 {
+    let mut input: Option<String> = None;
+    let mut status = 0;
+
+    for (i, command) in pipeline.iter().enumerate() {
+        if let Some(path) = &command.input {
+            let path = expand(path);
+            match read_file(&path) {
+                Ok(data) => input = Some(data),
+                Err(msg) => {
+                    eprintln!("{}: {}", path, msg);
+                    return 1;
+                }
+            }
+        }
+
+        let args: Vec<String> = command.argv.iter().map(expand).collect();
+        let argv: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+        // Строка из одних присваиваний `NAME=value` задаёт переменные
+        if !argv.is_empty() && argv.iter().all(|arg| assignment(arg).is_some()) {
+            for (name, value) in argv.iter().filter_map(|arg| assignment(arg)) {
+                set_var(name, value);
+            }
+            status = 0;
+            continue;
+        }
+
+        let last = i + 1 == pipeline.len();
+        if last && command.output.is_none() {
+            status = commands::execute(&argv, input.as_deref(), out);
+            continue;
+        }
+
+        let mut output = String::new();
+        status = commands::execute(&argv, input.as_deref(), &mut output);
+
+        input = match &command.output {
+            Some(redirect) => {
+                let path = expand(&redirect.path);
+                if let Err(msg) = write_file(&path, &output, redirect.append) {
+                    eprintln!("{}: {}", path, msg);
+                    status = 1;
+                }
+                // Вывод ушёл в файл, следующая команда получает пустой ввод
+                Some(String::new())
+            }
+            None => Some(output),
+        };
+    }
+    status
+}
+
+fn read_file(path: &str) -> Result<String, &'static str> {
+    let guard = FS.lock();
+    let fs = guard.as_ref().ok_or("No filesystem mounted")?;
+    let data = fs.read_file(path).map_err(|e| e.message())?;
+    Ok(String::from_utf8_lossy(data).into_owned())
+}
+
+fn write_file(path: &str, data: &str, append: bool) -> Result<(), &'static str> {
+    let mut guard = FS.lock();
+    let fs = guard.as_mut().ok_or("No filesystem mounted")?;
+    let result = if append {
+        fs.append_file(path, data.as_bytes())
+    } else {
+        fs.write_file(path, data.as_bytes())
+    };
+    result.map_err(|e| e.message())
+}
+
+pub fn init() {
+    register(Command {
+        name: "set",
+        aliases: &[],
+        description: "List or set shell variables",
+        usage: "set [NAME=VALUE...]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: set_action,
+    });
+    register(Command {
+        name: "export",
+        aliases: &[],
+        description: "Mark variables as exported",
+        usage: "export [NAME[=VALUE]...]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: export_action,
+    });
+    register(Command {
+        name: "unset",
+        aliases: &[],
+        description: "Remove shell variables",
+        usage: "unset NAME...",
+        args: ArgSpec {
+            options: &[],
+            min: 1,
+            max: usize::MAX,
+        },
+        handler: unset_action,
+    });
+    register(Command {
+        name: "sh",
+        aliases: &["source", "."],
+        description: "Run a shell script",
+        usage: "sh FILE [ARGS...]",
+        args: ArgSpec {
+            options: &[],
+            min: 1,
+            max: usize::MAX,
+        },
+        handler: sh_action,
+    });
+    register(Command {
+        name: "alias",
+        aliases: &[],
+        description: "List or define command aliases",
+        usage: "alias [NAME[=VALUE]...]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: alias_action,
+    });
+    register(Command {
+        name: "unalias",
+        aliases: &[],
+        description: "Remove command aliases",
+        usage: "unalias [-a] NAME...",
+        args: ArgSpec {
+            options: &[OptSpec {
+                name: "all",
+                short: Some('a'),
+                takes_value: false,
+            }],
+            min: 0,
+            max: usize::MAX,
+        },
+        handler: unalias_action,
+    });
+}
+
+fn set_action(io: &mut Io, args: &Args) -> i32 {
+    if args.positional.is_empty() {
+        for (name, var) in VARIABLES.lock().iter() {
+            let _ = writeln!(io, "{}={}", name, var.value);
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in &args.positional {
+        match assignment(arg) {
+            Some((name, value)) => set_var(name, value),
+            None => {
+                eprintln!("set: invalid assignment: {}", arg);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+fn export_action(io: &mut Io, args: &Args) -> i32 {
+    if args.positional.is_empty() {
+        for (name, var) in VARIABLES.lock().iter().filter(|(_, var)| var.exported) {
+            let _ = writeln!(io, "export {}={}", name, var.value);
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for &arg in &args.positional {
+        let (name, value) = match assignment(arg) {
+            Some((name, value)) => (name, Some(value)),
+            None if is_name(arg) => (arg, None),
+            None => {
+                eprintln!("export: invalid name: {}", arg);
+                status = 1;
+                continue;
+            }
+        };
+        let mut vars = VARIABLES.lock();
+        let var = vars.entry(String::from(name)).or_insert(Variable {
+            value: String::new(),
+            exported: false,
+        });
+        var.exported = true;
+        if let Some(value) = value {
+            var.value = String::from(value);
+        }
+    }
+    status
+}
+
+fn unset_action(_io: &mut Io, args: &Args) -> i32 {
+    let mut vars = VARIABLES.lock();
+    for name in &args.positional {
+        vars.remove(*name);
+    }
+    0
+}
+
+fn sh_action(io: &mut Io, args: &Args) -> i32 {
+    run_script(args.positional[0], &args.positional[1..], io.stdout)
+}
+
+// Имя псевдонима не должно содержать пробелов, кавычек и операторов
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "|&;<>'\"\\$=/#".contains(c))
+}
+
+fn alias_action(io: &mut Io, args: &Args) -> i32 {
+    if args.positional.is_empty() {
+        for (name, value) in ALIASES.lock().iter() {
+            let _ = writeln!(io, "alias {}='{}'", name, value);
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for &arg in &args.positional {
+        match arg.split_once('=') {
+            Some((name, value)) if is_alias_name(name) => {
+                ALIASES
+                    .lock()
+                    .insert(String::from(name), String::from(value));
+            }
+            Some(_) => {
+                eprintln!("alias: invalid name: {}", arg);
+                status = 1;
+            }
+            None => match ALIASES.lock().get(arg) {
+                Some(value) => {
+                    let _ = writeln!(io, "alias {}='{}'", arg, value);
+                }
+                None => {
+                    eprintln!("alias: {}: not found", arg);
+                    status = 1;
+                }
+            },
+        }
+    }
+    status
+}
+
+fn unalias_action(_io: &mut Io, args: &Args) -> i32 {
+    let mut aliases = ALIASES.lock();
+    if args.flag("all") {
+        aliases.clear();
+        return 0;
+    }
+
+    let mut status = 0;
+    for &name in &args.positional {
+        if aliases.remove(name).is_none() {
+            eprintln!("unalias: {}: not found", name);
+            status = 1;
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_script, run_text, set_var, write_file};
+    use alloc::string::String;
+
+    // Выполняет строку и возвращает код завершения и вывод
+    fn run_captured(line: &str) -> (i32, String) {
+        let mut out = String::new();
+        let status = run_text(line, &mut out).unwrap();
+        (status, out)
+    }
+
+    #[test_case]
+    fn quotes_and_variables() {
+        set_var("TEST_NAME", "world");
+        assert_eq!(
+            run_captured("echo \"hello $TEST_NAME\""),
+            (0, "hello world\n".into())
+        );
+        assert_eq!(
+            run_captured("echo '$TEST_NAME'"),
+            (0, "$TEST_NAME\n".into())
+        );
+        assert_eq!(
+            run_captured("echo a\\ b   c # comment"),
+            (0, "a b c\n".into())
+        );
+    }
+
+    #[test_case]
+    fn pipes_and_chaining() {
+        assert_eq!(run_captured("echo foo | grep -v bar"), (0, "foo\n".into()));
+        assert_eq!(
+            run_captured("false && echo no || echo yes"),
+            (0, "yes\n".into())
+        );
+        assert_eq!(run_captured("echo a; echo b"), (0, "a\nb\n".into()));
+        assert_eq!(run_captured("test 1 -eq 2").0, 1);
+    }
+
+    #[test_case]
+    fn blocks_and_redirection() {
+        assert_eq!(
+            run_captured("for x in 1 2 3; do echo $x; done"),
+            (0, "1\n2\n3\n".into())
+        );
+        assert_eq!(
+            run_captured("if [ a = b ]; then echo yes; else echo no; fi"),
+            (0, "no\n".into())
+        );
+        let line = "echo hi > /tmp/t; echo there >> /tmp/t; cat < /tmp/t; rm /tmp/t";
+        assert_eq!(run_captured(line), (0, "hi\nthere\n".into()));
+    }
+
+    #[test_case]
+    fn alias_applies_to_next_line() {
+        let script = "alias greet='echo hi'\ngreet there\n";
+        write_file("/tmp/alias.sh", script, false).unwrap();
+        let mut out = String::new();
+        assert_eq!(run_script("/tmp/alias.sh", &[], &mut out), 0);
+        assert_eq!(out, "hi there\n");
+        run_text("unalias greet; rm /tmp/alias.sh", &mut out).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{serial_print, serial_println};
+use x86_64::instructions::port::Port;
+
+// Устройство isa-debug-exit (см. test-args в Cargo.toml): QEMU завершается
+// с кодом (value << 1) | 1, поэтому 0x10 даёт 33 — успешный код для bootimage.
+const ISA_DEBUG_EXIT_PORT: u16 = 0xf4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum QemuExitCode {
+    Success = 0x10,
+    Failed = 0x11,
+}
+
+pub fn exit_qemu(exit_code: QemuExitCode) -> ! {
+    unsafe {
+        Port::new(ISA_DEBUG_EXIT_PORT).write(exit_code as u32);
+    }
+    // Не в QEMU: просто останавливаемся
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+pub trait Testable {
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn run(&self) {
+        serial_print!("{}...\t", core::any::type_name::<T>());
+        self();
+        serial_println!("[ok]");
+    }
+}
+
+// Запускается из `kernel_main` после инициализации ядра. Упавший тест
+// завершает QEMU из обработчика паники.
+pub fn test_runner(tests: &[&dyn Testable]) {
+    serial_println!("Running {} tests", tests.len());
+    for test in tests {
+        test.run();
+    }
+    exit_qemu(QemuExitCode::Success);
+}
@@ -0,0 +1,84 @@
+use crate::commands::{register, Command, Io};
+use crate::constants::{COLOR_ERROR, COLOR_INFO, COLOR_STATUS_BAR, COLOR_TEXT};
+use crate::eprintln;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU8, Ordering};
+use neonforge_core::args::{ArgSpec, Args};
+
+// Цвета интерфейса (атрибуты VGA: фон в старших 4 битах, символ в младших).
+// Значения по умолчанию берутся из constants, меняются командой `color`.
+static TEXT: AtomicU8 = AtomicU8::new(COLOR_TEXT);
+static ERROR: AtomicU8 = AtomicU8::new(COLOR_ERROR);
+static INFO: AtomicU8 = AtomicU8::new(COLOR_INFO);
+static STATUS_BAR: AtomicU8 = AtomicU8::new(COLOR_STATUS_BAR);
+
+static COLORS: [(&str, &AtomicU8); 4] = [
+    ("text", &TEXT),
+    ("error", &ERROR),
+    ("info", &INFO),
+    ("status", &STATUS_BAR),
+];
+
+pub fn text() -> u8 {
+    TEXT.load(Ordering::Relaxed)
+}
+
+pub fn error() -> u8 {
+    ERROR.load(Ordering::Relaxed)
+}
+
+pub fn info() -> u8 {
+    INFO.load(Ordering::Relaxed)
+}
+
+pub fn status_bar() -> u8 {
+    STATUS_BAR.load(Ordering::Relaxed)
+}
+
+pub fn init() {
+    register(Command {
+        name: "color",
+        aliases: &[],
+        description: "Show or change interface colours",
+        usage: "color [text|error|info|status ATTR]",
+        args: ArgSpec {
+            options: &[],
+            min: 0,
+            max: 2,
+        },
+        handler: color_action,
+    });
+}
+
+fn color_action(io: &mut Io, args: &Args) -> i32 {
+    match args.positional[..] {
+        [] => {
+            for (name, color) in &COLORS {
+                let _ = writeln!(io, "{:8}0x{:02x}", name, color.load(Ordering::Relaxed));
+            }
+            0
+        }
+        [name, value] => {
+            let Some((_, color)) = COLORS.iter().find(|(n, _)| *n == name) else {
+                eprintln!("color: unknown element: {}", name);
+                return 1;
+            };
+            // Атрибут в шестнадцатеричном виде: 0x1f или 1f
+            let digits = value.strip_prefix("0x").unwrap_or(value);
+            match u8::from_str_radix(digits, 16) {
+                Ok(value) => {
+                    color.store(value, Ordering::Relaxed);
+                    0
+                }
+                Err(_) => {
+                    eprintln!("color: invalid attribute: {}", value);
+                    1
+                }
+            }
+        }
+        _ => {
+            eprintln!("Usage: color [text|error|info|status ATTR]");
+            2
+        }
+    }
+}
@@ -1,4 +1,38 @@
-use crate::constants::{COLOR_STATUS_BAR, COLS, ROWS};
+use crate::constants::{COLS, ROWS};
+use crate::pci::{self, Address, Device, Driver, Match};
+use crate::{log, memory, theme};
+use core::fmt;
+use neonforge_core::pci::Bar;
+use spin::Mutex;
+
+// Текст выводится через окно 0xB8000 VGA-совместимого адаптера. Bochs/QEMU
+// stdvga подключается и как secondary-vga, без VGA-класса.
+const PCI_MATCHES: &[Match] = &[
+    Match::Class {
+        class: 0x03,
+        subclass: 0x00,
+    },
+    Match::Id {
+        vendor: 0x1234,
+        device: 0x1111,
+    },
+];
+
+// Линейный буфер кадра адаптера, найденный драйвером. Текстовый режим его
+// не использует; он отображён заранее для графического режима.
+#[derive(Clone, Copy)]
+pub struct Framebuffer {
+    pub device: Address,
+    pub physical: u64,
+    pub virt: u64, // Адрес в окне MMIO
+    pub size: u64,
+}
+
+static FRAMEBUFFER: Mutex<Option<Framebuffer>> = Mutex::new(None);
+
+pub fn framebuffer() -> Option<Framebuffer> {
+    *FRAMEBUFFER.lock()
+}
 
 pub fn write_char(row: usize, col: usize, character: u8, color: u8) {
     let vga_buffer = 0xb8000 as *mut u8; // Адрес VGA буфера
@@ -13,6 +47,32 @@ pub fn write_char(row: usize, col: usize, character: u8, color: u8) {
     }
 }
 
+// Форматированный вывод прямо в видеопамять, без BUFFER (панель состояния)
+pub struct Writer {
+    row: usize,
+    col: usize,
+    color: u8,
+}
+
+impl Writer {
+    pub fn new(row: usize, col: usize, color: u8) -> Self {
+        Writer { row, col, color }
+    }
+}
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if self.col >= COLS {
+                break;
+            }
+            write_char(self.row, self.col, byte, self.color);
+            self.col += 1;
+        }
+        Ok(())
+    }
+}
+
 pub fn clear_screen(width: u16, height: u16) {
     let vga_buffer = 0xb8000 as *mut u8;
     for i in 0..(width as usize * height as usize * 2) {
@@ -22,7 +82,7 @@ pub fn clear_screen(width: u16, height: u16) {
     }
 }
 
-pub fn print_buffer(buffer: *mut [[u8; COLS]; ROWS]) {
+pub fn print_buffer(buffer: *const [[u8; COLS]; ROWS], colors: *const [[u8; COLS]; ROWS]) {
     let width = COLS;
     let vga_buffer = 0xb8000 as *mut u8;
     unsafe {
@@ -31,21 +91,88 @@ pub fn print_buffer(buffer: *mut [[u8; COLS]; ROWS]) {
                 if row == 24 {
                     *vga_buffer.offset((24 as isize * 80 as isize + col as isize) * 2) = b'_';
                     *vga_buffer.offset((24 as isize * 80 as isize + col as isize) * 2 + 1) =
-                        COLOR_STATUS_BAR;
+                        theme::status_bar();
                 }
                 if (*buffer)[row][col] != 0 {
+                    let color = match (*colors)[row][col] {
+                        0 => theme::text(),
+                        color => color,
+                    };
                     *vga_buffer.offset((row as isize * width as isize + col as isize) * 2) =
                         (*buffer)[row][col];
                     *vga_buffer.offset((row as isize * width as isize + col as isize) * 2 + 1) =
-                        0x07;
+                        color;
                 }
             }
         }
     }
 }
 
-pub fn write_string(row: usize, col: usize, s: &str, color: u8) {
-    for (i, byte) in s.bytes().enumerate() {
-        write_char(row, col + i, byte, color);
+pub fn register_driver() {
+    pci::register_driver(Driver {
+        name: "vga",
+        matches: PCI_MATCHES,
+        probe,
+    });
+}
+
+// Линейный буфер кадра — первый предвыбираемый BAR памяти
+fn probe(device: &Device) -> Result<(), &'static str> {
+    let (address, size) = device
+        .bars
+        .iter()
+        .find_map(|&(_, bar)| match bar {
+            Bar::Memory {
+                address,
+                size,
+                prefetchable: true,
+                ..
+            } => Some((address, size)),
+            _ => None,
+        })
+        .ok_or("no framebuffer BAR")?;
+    // Драйвер обслуживает один адаптер
+    let mut framebuffer = FRAMEBUFFER.lock();
+    if framebuffer.is_some() {
+        return Err("another adapter is already bound");
+    }
+    let virt = memory::map_mmio(address, size as usize)? as u64;
+    *framebuffer = Some(Framebuffer {
+        device: device.address,
+        physical: address,
+        virt,
+        size,
+    });
+    log::info!(
+        "VGA {}: framebuffer at {:#x}, {} KiB",
+        device.address,
+        address,
+        size / 1024
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::framebuffer;
+    use crate::pci::devices;
+
+    // QEMU по умолчанию подключает stdvga с буфером кадра в BAR0
+    #[test_case]
+    fn framebuffer_is_mapped() {
+        let framebuffer = framebuffer().unwrap();
+        assert!(framebuffer.size >= 1024 * 1024);
+        let device = devices()
+            .into_iter()
+            .find(|device| device.address == framebuffer.device)
+            .unwrap();
+        assert_eq!(device.driver, Some("vga"));
+        // Запись через отображение читается обратно
+        let pixel = framebuffer.virt as *mut u32;
+        unsafe {
+            pixel.write_volatile(0x00FF_00FF);
+            assert_eq!(pixel.read_volatile(), 0x00FF_00FF);
+            pixel.write_volatile(0);
+        }
     }
 }